@@ -0,0 +1,460 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::{
+    fixedtimestep::{app::AppLooplessFixedTimestepExt, FixedTimesteps},
+    prelude::{ConditionHelpers, IntoConditionalSystem},
+};
+
+use crate::{
+    enemy::{KillEnemyHitbox, KillPlayerHitbox},
+    interfaces::AudioVolume,
+    kinematic_physics::{
+        character_controller, physics_dt, CCAcceleration, CCVelocity, KinematicGravity,
+        PhysicsInterpolation, MOVEMENT_SUBSTAGE, PHYSICS_TIMESTEP,
+    },
+    level::{spawn_meter, BAR_HEIGHT, BAR_WIDTH, FILL_WIDTH},
+    movement_config::MovementConfig,
+    player::{Player, PLAYER_RADIUS},
+    projectiles::spawn_projectile,
+    states::{GameState, PauseState},
+    ActorDead, InGameItem, SoundCollection, SystemOrderLabel, TextureHandles,
+};
+
+pub const BOSS_RADIUS: f32 = PLAYER_RADIUS * 2.;
+const BOSS_HEALTH: u32 = 5;
+// unlike any enemy behaviour, so he can't be mistaken for one
+const BOSS_COLOR: Color = Color::FUCHSIA;
+
+// how long he can't be hurt again after a stomp, blinking the whole time
+const BOSS_INVULNERABLE_TIME: f32 = 1.;
+const BOSS_BLINK_TIME: f32 = 0.1;
+const BOSS_STOMP_BOUNCE: f32 = 0.4;
+
+// pause between attacks, the best time to get a stomp in
+const BOSS_REST_TIME: f32 = 1.2;
+
+// units per tick
+const BOSS_CHARGE_SPEED: f32 = 0.35;
+const BOSS_CHARGE_TIME: f32 = 1.5;
+
+const BOSS_LEAP_SPEED: f32 = 1.4;
+const BOSS_LEAP_MAX_DRIFT: f32 = 0.5;
+// the "ha"s sent along the ground either way when he lands
+const BOSS_SLAM_SHOT_SPEED: f32 = 0.3;
+
+const BOSS_LAUGH_SHOTS: u32 = 6;
+const BOSS_LAUGH_INTERVAL: f32 = 0.3;
+// angle between the shots of a volley
+const BOSS_LAUGH_SPREAD: f32 = 0.2;
+
+/// Mr Fuqheed. Leaves the player alone until they stomp him, then cycles
+/// through his attacks until he runs out of health
+#[derive(Component)]
+pub struct Boss {
+    pub health: u32,
+    phase: BossPhase,
+    /// physics time spent in the current phase
+    phase_time: f32,
+    /// attacks made so far, picks the next one
+    attacks: usize,
+    invulnerable: f32,
+}
+
+impl Default for Boss {
+    fn default() -> Self {
+        Self {
+            health: BOSS_HEALTH,
+            phase: BossPhase::Waiting,
+            phase_time: 0.,
+            attacks: 0,
+            invulnerable: 0.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BossPhase {
+    /// nobody's picked a fight yet
+    Waiting,
+    /// catching his breath between attacks
+    Resting,
+    /// running at where the player was
+    Charging { dir: f32 },
+    /// jumping onto the player, sending "ha"s out either side on landing
+    Leaping { drift: f32, left_ground: bool },
+    /// standing still and laughing at the player
+    Laughing { shots: u32 },
+}
+
+impl Boss {
+    fn enter(&mut self, phase: BossPhase) {
+        self.phase = phase;
+        self.phase_time = 0.;
+    }
+
+    pub fn fighting(&self) -> bool {
+        self.phase != BossPhase::Waiting
+    }
+}
+
+/// How the run went for Mr Fuqheed, the endings take it into account
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BossResult {
+    /// no boss this run, or still to reach the end of his level
+    #[default]
+    Unmet,
+    /// walked past him to the end of the level
+    Spared,
+    Defeated,
+}
+
+#[derive(Component)]
+struct BossFace;
+
+#[derive(Component)]
+struct BossHealthBar;
+
+#[derive(Component)]
+struct BossHealthFill;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemLabel)]
+enum BossLabel {
+    Stomp,
+}
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BossResult>()
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                MOVEMENT_SUBSTAGE,
+                player_stomp_boss
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(PauseState::Running)
+                    .label(BossLabel::Stomp)
+                    .after(SystemOrderLabel::Input)
+                    .before(SystemOrderLabel::Movement),
+            )
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                MOVEMENT_SUBSTAGE,
+                boss_attack
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(PauseState::Running)
+                    .after(BossLabel::Stomp)
+                    .before(SystemOrderLabel::Movement),
+            )
+            .add_system(spawn_boss_health_bar.run_in_state(GameState::InGame))
+            .add_system(update_boss_health_bar.run_in_state(GameState::InGame))
+            .add_system(update_boss_looks);
+    }
+}
+
+pub fn spawn_boss(
+    commands: &mut Commands,
+    texture_handles: &TextureHandles,
+    position: Vec2,
+) -> Entity {
+    let sprite_size = Some(Vec2::splat(BOSS_RADIUS * 2.));
+
+    commands
+        .spawn(Boss::default())
+        .insert(RigidBody::KinematicPositionBased)
+        .insert(Collider::ball(BOSS_RADIUS))
+        .insert(character_controller())
+        .insert(ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_KINEMATIC)
+        .insert(CCVelocity(Vec2::ZERO))
+        .insert(CCAcceleration(Vec2::ZERO))
+        .insert(KinematicGravity)
+        .insert(PhysicsInterpolation::default())
+        .insert(KillPlayerHitbox)
+        .insert(InGameItem)
+        .insert(TransformBundle::from_transform(
+            Transform::from_translation(position.extend(10.)),
+        ))
+        .insert(VisibilityBundle::default())
+        .with_children(|cb| {
+            // same as an enemy's, scaled up
+            cb.spawn(Collider::ball(BOSS_RADIUS - 0.2))
+                .insert(TransformBundle::from_transform(Transform::from_xyz(
+                    0.0, 0.6, 0.0,
+                )))
+                .insert(KillEnemyHitbox);
+
+            cb.spawn(SpriteBundle {
+                texture: texture_handles.char_outline.clone().unwrap(),
+                sprite: Sprite {
+                    color: Color::WHITE,
+                    custom_size: sprite_size,
+                    ..default()
+                },
+                ..default()
+            });
+
+            cb.spawn(SpriteBundle {
+                texture: texture_handles.char_body.clone().unwrap(),
+                sprite: Sprite {
+                    color: BOSS_COLOR,
+                    custom_size: sprite_size,
+                    ..default()
+                },
+                ..default()
+            });
+
+            cb.spawn(SpriteBundle {
+                texture: texture_handles.char_face_neutral.clone().unwrap(),
+                sprite: Sprite {
+                    color: Color::WHITE,
+                    custom_size: sprite_size,
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(BossFace);
+        })
+        .id()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn player_stomp_boss(
+    mut commands: Commands,
+    mut q_player: Query<(&KinematicCharacterControllerOutput, &mut CCAcceleration), With<Player>>,
+    q_attackboxes: Query<&Parent, With<KillEnemyHitbox>>,
+    mut q_bosses: Query<&mut Boss>,
+    mut boss_result: ResMut<BossResult>,
+    audio: Res<Audio>,
+    sound_collection: Res<SoundCollection>,
+    audio_volume: Res<AudioVolume>,
+) {
+    for (output, mut acc) in &mut q_player {
+        let stomped = output
+            .collisions
+            .iter()
+            .filter_map(|collision| q_attackboxes.get(collision.entity).ok())
+            .find(|parent| q_bosses.contains(parent.get()));
+        let Some(parent) = stomped else {
+            continue;
+        };
+        let Ok(mut boss) = q_bosses.get_mut(parent.get()) else {
+            continue;
+        };
+
+        // bounce off whether it hurts or not, so nobody gets stuck on top of him
+        acc.0.y += BOSS_STOMP_BOUNCE;
+
+        if boss.invulnerable > 0. {
+            continue;
+        }
+
+        audio.play_with_settings(
+            sound_collection.kill.clone(),
+            PlaybackSettings::ONCE.with_volume(audio_volume.0),
+        );
+
+        boss.health = boss.health.saturating_sub(1);
+        boss.invulnerable = BOSS_INVULNERABLE_TIME;
+
+        if boss.health == 0 {
+            commands.entity(parent.get()).insert(ActorDead);
+            *boss_result = BossResult::Defeated;
+        } else if !boss.fighting() {
+            boss.enter(BossPhase::Resting);
+        }
+    }
+}
+
+fn boss_attack(
+    mut commands: Commands,
+    timesteps: Res<FixedTimesteps>,
+    q_player: Query<&Transform, With<Player>>,
+    mut q_bosses: Query<(
+        &Transform,
+        &mut CCVelocity,
+        &KinematicCharacterControllerOutput,
+        &mut Boss,
+    )>,
+    config: Res<MovementConfig>,
+    texture_handles: Res<TextureHandles>,
+) {
+    let dt = physics_dt(&timesteps);
+    let Ok(player_transform) = q_player.get_single() else {
+        return;
+    };
+    let target = player_transform.translation.truncate();
+
+    for (transform, mut vel, output, mut boss) in &mut q_bosses {
+        boss.phase_time += dt;
+        boss.invulnerable = (boss.invulnerable - dt).max(0.);
+
+        let position = transform.translation.truncate();
+        let towards = (target.x - position.x).signum();
+        // friction takes its share before he moves, ask for that much more
+        let walk = |speed: f32| speed * config.friction;
+
+        match boss.phase {
+            BossPhase::Waiting => vel.0.x = 0.,
+            BossPhase::Resting => {
+                vel.0.x = 0.;
+
+                if boss.phase_time >= BOSS_REST_TIME && output.grounded {
+                    let next = match boss.attacks % 3 {
+                        0 => BossPhase::Charging { dir: towards },
+                        1 => {
+                            // land about where the player is, given how long he's up for
+                            let air_time = 2. * BOSS_LEAP_SPEED / config.gravity;
+                            let drift = ((target.x - position.x) / air_time)
+                                .clamp(-BOSS_LEAP_MAX_DRIFT, BOSS_LEAP_MAX_DRIFT);
+                            vel.0.y = BOSS_LEAP_SPEED;
+                            BossPhase::Leaping {
+                                drift,
+                                left_ground: false,
+                            }
+                        }
+                        _ => BossPhase::Laughing { shots: 0 },
+                    };
+                    boss.attacks += 1;
+                    boss.enter(next);
+                }
+            }
+            BossPhase::Charging { dir } => {
+                vel.0.x = walk(BOSS_CHARGE_SPEED * dir);
+
+                if boss.phase_time >= BOSS_CHARGE_TIME {
+                    boss.enter(BossPhase::Resting);
+                }
+            }
+            BossPhase::Leaping { drift, left_ground } => {
+                vel.0.x = walk(drift);
+
+                if !output.grounded {
+                    boss.phase = BossPhase::Leaping {
+                        drift,
+                        left_ground: true,
+                    };
+                } else if left_ground {
+                    // slam
+                    let feet = position - Vec2::Y * (BOSS_RADIUS - 0.5);
+                    for dir in [-1., 1.] {
+                        spawn_projectile(
+                            &mut commands,
+                            &texture_handles,
+                            feet + Vec2::X * dir * BOSS_RADIUS,
+                            Vec2::X * dir * BOSS_SLAM_SHOT_SPEED,
+                        );
+                    }
+                    boss.enter(BossPhase::Resting);
+                }
+            }
+            BossPhase::Laughing { shots } => {
+                vel.0.x = 0.;
+
+                if shots == BOSS_LAUGH_SHOTS {
+                    boss.enter(BossPhase::Resting);
+                } else if boss.phase_time >= shots as f32 * BOSS_LAUGH_INTERVAL {
+                    // fan out around the player, a little up, straight at them, a little down
+                    let spread = BOSS_LAUGH_SPREAD * ((shots % 3) as f32 - 1.);
+                    let aim = (target - position).normalize_or_zero();
+                    let dir = Vec2::from_angle(spread).rotate(aim);
+
+                    spawn_projectile(
+                        &mut commands,
+                        &texture_handles,
+                        position + dir * BOSS_RADIUS,
+                        dir * config.enemy_shot_speed,
+                    );
+                    boss.phase = BossPhase::Laughing { shots: shots + 1 };
+                }
+            }
+        }
+    }
+}
+
+fn update_boss_looks(
+    q_bosses: Query<(&Boss, &Children), Changed<Boss>>,
+    mut q_visibility: Query<&mut Visibility>,
+    mut q_faces: Query<&mut Handle<Image>, With<BossFace>>,
+    texture_handles: Res<TextureHandles>,
+) {
+    for (boss, children) in &q_bosses {
+        let face = match boss.phase {
+            BossPhase::Waiting => &texture_handles.char_face_neutral,
+            BossPhase::Laughing { .. } => &texture_handles.char_face_laughing,
+            _ => &texture_handles.char_face_angry,
+        };
+
+        // blink while he can't be hurt
+        let blinks = (boss.invulnerable / BOSS_BLINK_TIME) as u32;
+        let visible = boss.invulnerable == 0. || blinks.is_multiple_of(2);
+
+        for &child in children {
+            if let Ok(mut handle) = q_faces.get_mut(child) {
+                if *handle != *face.as_ref().unwrap() {
+                    *handle = face.clone().unwrap();
+                }
+            }
+            if let Ok(mut visibility) = q_visibility.get_mut(child) {
+                if visibility.is_visible != visible {
+                    visibility.is_visible = visible;
+                }
+            }
+        }
+    }
+}
+
+fn spawn_boss_health_bar(
+    mut commands: Commands,
+    q_bosses: Query<(), Added<Boss>>,
+    texture_handles: Res<TextureHandles>,
+) {
+    for _ in &q_bosses {
+        let bar = spawn_meter(&mut commands, &texture_handles, default(), BossHealthFill);
+        // along the top in the middle, out of the respect meter's way
+        commands
+            .entity(bar)
+            .insert(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(50.),
+                    ..default()
+                },
+                margin: UiRect {
+                    left: Val::Px(-BAR_WIDTH / 2.),
+                    ..default()
+                },
+                size: Size::new(Val::Px(BAR_WIDTH), Val::Px(BAR_HEIGHT)),
+                // hidden until the fight starts
+                display: Display::None,
+                ..default()
+            })
+            .insert(BossHealthBar)
+            .insert(InGameItem);
+    }
+}
+
+fn update_boss_health_bar(
+    q_bosses: Query<&Boss>,
+    mut q_bar: Query<&mut Style, (With<BossHealthBar>, Without<BossHealthFill>)>,
+    mut q_fill: Query<&mut Style, (With<BossHealthFill>, Without<BossHealthBar>)>,
+) {
+    let boss = q_bosses.iter().next();
+    let fighting = boss.is_some_and(|boss| boss.fighting());
+
+    for mut style in &mut q_bar {
+        let display = if fighting {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if style.display != display {
+            style.display = display;
+        }
+    }
+
+    if let Some(boss) = boss {
+        for mut style in &mut q_fill {
+            style.size.width = Val::Px(FILL_WIDTH * boss.health as f32 / BOSS_HEALTH as f32);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::level_loader::{BoxData, EnemyData, HazardData, MovingPlatformData, SlopeData};
+
+// oldest commands fall off the end past this
+const HISTORY_LIMIT: usize = 100;
+
+/// Stable id for a level editor item. Undo and redo respawn entities, so
+/// commands can't hold onto an `Entity`
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EditorId(pub u64);
+
+/// Enough about an editor item to spawn it again
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditorItem {
+    Box(BoxData),
+    Enemy(EnemyData),
+    PlayerSpawn(Vec2),
+    Trigger(BoxData),
+    Checkpoint(Vec2),
+    Platform(BoxData),
+    MovingPlatform(MovingPlatformData),
+    CrumblingPlatform(BoxData),
+    Hazard(HazardData),
+    Slope(SlopeData),
+    Boss(Vec2),
+}
+
+/// A reversible editor operation
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditorCommand {
+    Spawn {
+        id: EditorId,
+        item: EditorItem,
+    },
+    Despawn {
+        id: EditorId,
+        item: EditorItem,
+    },
+    Modify {
+        id: EditorId,
+        before: EditorItem,
+        after: EditorItem,
+    },
+}
+
+impl EditorCommand {
+    pub fn inverse(self) -> Self {
+        match self {
+            EditorCommand::Spawn { id, item } => EditorCommand::Despawn { id, item },
+            EditorCommand::Despawn { id, item } => EditorCommand::Spawn { id, item },
+            EditorCommand::Modify { id, before, after } => EditorCommand::Modify {
+                id,
+                before: after,
+                after: before,
+            },
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct EditorHistory {
+    undo: VecDeque<EditorCommand>,
+    redo: Vec<EditorCommand>,
+    next_id: u64,
+}
+
+impl EditorHistory {
+    pub fn new_id(&mut self) -> EditorId {
+        self.next_id += 1;
+        EditorId(self.next_id)
+    }
+
+    /// Record a command that has already been applied
+    pub fn push(&mut self, command: EditorCommand) {
+        self.redo.clear();
+        self.undo.push_back(command);
+
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.pop_front();
+        }
+    }
+
+    /// The command that undoes the last one, to be applied by the caller
+    pub fn undo(&mut self) -> Option<EditorCommand> {
+        let command = self.undo.pop_back()?;
+        self.redo.push(command.clone());
+        Some(command.inverse())
+    }
+
+    pub fn redo(&mut self) -> Option<EditorCommand> {
+        let command = self.redo.pop()?;
+        self.undo.push_back(command.clone());
+        Some(command)
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
@@ -42,9 +42,6 @@ pub enum Endings {
 #[derive(Component)]
 struct EndScreenTag;
 
-#[derive(Component)]
-struct BigBoxTag;
-
 #[derive(Component)]
 struct RootNodeTag;
 
@@ -1,4 +1,4 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::EntityCommands, prelude::*};
 use bevy_particle_systems::{
     ColorPoint, JitteredValue, ParticleSpace, ParticleSystem, ParticleSystemBundle,
     ParticleTexture, Playing,
@@ -6,7 +6,10 @@ use bevy_particle_systems::{
 use bevy_rapier2d::prelude::*;
 
 use crate::{
-    kinematic_physics::{CCAcceleration, CCVelocity, KinematicGravity},
+    kinematic_physics::{
+        character_controller, CCAcceleration, CCVelocity, KinematicGravity, PhysicsInterpolation,
+    },
+    level_loader::{EnemyBehaviour, EnemyData},
     player::PLAYER_RADIUS,
     InGameItem, TextureHandles,
 };
@@ -20,14 +23,155 @@ pub struct KillPlayerHitbox;
 #[derive(Component)]
 pub struct EnemyMover {
     pub dir: f32,
+    pub turn_at_ledges: bool,
+    /// patrol bounds, as distances either side of `home`
+    pub patrol_left: Option<f32>,
+    pub patrol_right: Option<f32>,
+    /// x position it was spawned at
+    pub home: f32,
+}
+
+/// Stomps left before it dies, and how long until another one counts
+#[derive(Component)]
+pub struct EnemyHealth {
+    pub health: u32,
+    pub invulnerable: f32,
+}
+
+/// Knocked silly by a stomp it survived. Doesn't move or attack until it
+/// comes round
+#[derive(Component)]
+pub struct EnemyStunned {
+    pub remaining: f32,
+}
+
+#[derive(Component)]
+struct EnemyFace;
+
+// how often a hurt enemy blinks while it can't be hurt again
+const ENEMY_BLINK_TIME: f32 = 0.08;
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(update_enemy_looks);
+    }
 }
 
 impl Default for EnemyMover {
     fn default() -> Self {
-        Self { dir: 1. }
+        Self {
+            dir: 1.,
+            turn_at_ledges: false,
+            patrol_left: None,
+            patrol_right: None,
+            home: 0.,
+        }
     }
 }
 
+/// Runs at the player whenever it can see them
+#[derive(Component)]
+pub struct EnemyChaser;
+
+/// Hops whenever it's been on the ground long enough
+#[derive(Component, Default)]
+pub struct EnemyJumper {
+    pub since_jump: f32,
+}
+
+/// Ignores gravity and bobs up and down around `base_y`, an `EnemyMover`
+/// takes it back and forth
+#[derive(Component)]
+pub struct EnemyFlyer {
+    pub base_y: f32,
+    pub time: f32,
+}
+
+/// Fires a projectile at the player whenever it can see them and has waited
+/// long enough since the last one
+#[derive(Component, Default)]
+pub struct EnemyShooter {
+    pub since_shot: f32,
+}
+
+/// Every behaviour in the order the editor cycles through them
+pub const BEHAVIOURS: [EnemyBehaviour; 6] = [
+    EnemyBehaviour::Static,
+    EnemyBehaviour::Mover,
+    EnemyBehaviour::Chaser,
+    EnemyBehaviour::Jumper,
+    EnemyBehaviour::Flyer,
+    EnemyBehaviour::Shooter,
+];
+
+/// How an enemy with some behaviour looks and what drives it, on top of what
+/// every enemy has
+pub struct BehaviourEntry {
+    pub color: Color,
+    pub insert: fn(&mut EntityCommands, &EnemyData),
+}
+
+/// The behaviour registry. A new behaviour needs an `EnemyBehaviour`, an entry
+/// here and systems for whatever components it inserts
+pub fn behaviour_entry(behaviour: EnemyBehaviour) -> BehaviourEntry {
+    match behaviour {
+        EnemyBehaviour::Static => BehaviourEntry {
+            color: Color::GREEN,
+            insert: |_, _| {},
+        },
+        EnemyBehaviour::Mover => BehaviourEntry {
+            color: Color::BLUE,
+            insert: insert_mover,
+        },
+        EnemyBehaviour::Chaser => BehaviourEntry {
+            color: Color::ORANGE_RED,
+            insert: |e, _| {
+                e.insert(EnemyChaser);
+            },
+        },
+        EnemyBehaviour::Jumper => BehaviourEntry {
+            color: Color::YELLOW,
+            insert: |e, _| {
+                e.insert(EnemyJumper::default());
+            },
+        },
+        EnemyBehaviour::Flyer => BehaviourEntry {
+            color: Color::PURPLE,
+            insert: |e, enemy| {
+                insert_mover(e, enemy);
+                e.remove::<KinematicGravity>()
+                    .insert(EnemyFlyer {
+                        base_y: enemy.position.y,
+                        time: 0.,
+                    })
+                    // nothing to snap to in the air
+                    .insert(KinematicCharacterController {
+                        snap_to_ground: None,
+                        ..character_controller()
+                    });
+            },
+        },
+        EnemyBehaviour::Shooter => BehaviourEntry {
+            color: Color::CYAN,
+            insert: |e, _| {
+                e.insert(EnemyShooter::default());
+            },
+        },
+    }
+}
+
+fn insert_mover(e: &mut EntityCommands, enemy: &EnemyData) {
+    e.insert(EnemyMover {
+        turn_at_ledges: enemy.turn_at_ledges,
+        patrol_left: enemy.patrol_left,
+        patrol_right: enemy.patrol_right,
+        home: enemy.position.x,
+        ..default()
+    });
+}
+
 #[derive(Bundle)]
 struct EnemyBundle {
     pub rb: RigidBody,
@@ -36,11 +180,13 @@ struct EnemyBundle {
     pub velocity: CCVelocity,
     pub acceleration: CCAcceleration,
     pub transform_bundle: TransformBundle,
+    pub interpolation: PhysicsInterpolation,
+    pub health: EnemyHealth,
 
     _kph: KillPlayerHitbox,
     _vb: VisibilityBundle,
     _kg: KinematicGravity,
-    _e: Enemy,
+    enemy: Enemy,
     _igi: InGameItem,
 }
 
@@ -49,17 +195,19 @@ impl Default for EnemyBundle {
         Self {
             rb: RigidBody::KinematicPositionBased,
             collider: Collider::ball(PLAYER_RADIUS),
-            controller: KinematicCharacterController {
-                apply_impulse_to_dynamic_bodies: true,
-                ..default()
-            },
+            controller: character_controller(),
             transform_bundle: TransformBundle::from_transform(Transform::from_xyz(0., 0., 10.)),
             velocity: CCVelocity(Vec2::new(0.0, 0.0)),
             acceleration: CCAcceleration(Vec2::new(0.0, 0.0)),
+            interpolation: PhysicsInterpolation::default(),
+            health: EnemyHealth {
+                health: 1,
+                invulnerable: 0.,
+            },
 
             _kph: KillPlayerHitbox,
             _vb: VisibilityBundle::default(),
-            _e: Enemy,
+            enemy: Enemy(EnemyBehaviour::Static),
             _kg: KinematicGravity,
             _igi: InGameItem,
         }
@@ -67,31 +215,33 @@ impl Default for EnemyBundle {
 }
 
 #[derive(Component)]
-struct StaticEnemy;
-
-#[derive(Component)]
-pub struct Enemy;
+pub struct Enemy(pub EnemyBehaviour);
 
 pub fn spawn_enemy(
     commands: &mut Commands,
     texture_handles: &TextureHandles,
-    position: Vec3,
-    mover: bool,
+    enemy: EnemyData,
 ) -> Entity {
     let sprite_size = Some(Vec2::new(PLAYER_RADIUS * 2., PLAYER_RADIUS * 2.));
 
-    let mut fade_out = Vec::new();
-    fade_out.push(ColorPoint::new(Color::WHITE, 0.));
-    fade_out.push(ColorPoint::new(Color::NONE, 1.));
+    let fade_out = vec![
+        ColorPoint::new(Color::WHITE, 0.),
+        ColorPoint::new(Color::NONE, 1.),
+    ];
 
-    let enemy_color = if mover { Color::BLUE } else { Color::GREEN };
+    let behaviour = behaviour_entry(enemy.behaviour);
 
     // Enemy
     let e = commands
         .spawn(EnemyBundle {
             transform_bundle: TransformBundle::from_transform(Transform::from_translation(
-                position,
+                enemy.position.extend(10.),
             )),
+            enemy: Enemy(enemy.behaviour),
+            health: EnemyHealth {
+                health: enemy.health,
+                invulnerable: 0.,
+            },
             ..default()
         })
         .insert(ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_KINEMATIC)
@@ -139,7 +289,7 @@ pub fn spawn_enemy(
             cb.spawn(SpriteBundle {
                 texture: texture_handles.char_body.clone().unwrap(),
                 sprite: Sprite {
-                    color: enemy_color,
+                    color: behaviour.color,
                     custom_size: sprite_size,
                     ..default()
                 },
@@ -154,13 +304,43 @@ pub fn spawn_enemy(
                     ..default()
                 },
                 ..default()
-            });
+            })
+            .insert(EnemyFace);
         })
         .id();
 
-    if mover {
-        commands.entity(e).insert(EnemyMover::default());
-    }
+    (behaviour.insert)(&mut commands.entity(e), &enemy);
 
     e
 }
+
+fn update_enemy_looks(
+    q_enemies: Query<(&EnemyHealth, Option<&EnemyStunned>, &Children), With<Enemy>>,
+    mut q_sprites: Query<&mut Visibility, With<Sprite>>,
+    mut q_faces: Query<&mut Handle<Image>, With<EnemyFace>>,
+    texture_handles: Res<TextureHandles>,
+) {
+    for (health, stunned, children) in &q_enemies {
+        // not laughing now
+        let face = match stunned {
+            Some(_) => &texture_handles.char_face_neutral,
+            None => &texture_handles.char_face_laughing,
+        };
+
+        let blinks = (health.invulnerable / ENEMY_BLINK_TIME) as u32;
+        let visible = health.invulnerable == 0. || blinks.is_multiple_of(2);
+
+        for &child in children {
+            if let Ok(mut handle) = q_faces.get_mut(child) {
+                if *handle != *face.as_ref().unwrap() {
+                    *handle = face.clone().unwrap();
+                }
+            }
+            if let Ok(mut visibility) = q_sprites.get_mut(child) {
+                if visibility.is_visible != visible {
+                    visibility.is_visible = visible;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use bevy::{
+    prelude::*,
+    render::mesh::{PrimitiveTopology, VertexAttributeValues},
+    sprite::MaterialMesh2dBundle,
+};
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::{ConditionHelpers, IntoConditionalSystem};
+
+use crate::{
+    interfaces::AudioVolume,
+    level::{BoxBottomRight, BoxTopLeft},
+    level_loader::{BoxData, HazardData, HazardKind},
+    player::{Player, PlayerHurt},
+    states::{GameState, PauseState},
+    Actor, ActorDead, InGameItem, SoundCollection, TextureHandles,
+};
+
+// roughly how wide each spike is, they're stretched to fill the hazard exactly
+const SPIKE_WIDTH: f32 = 1.;
+
+const KILL_ZONE_COLOR: Color = Color::rgba(1., 0.3, 0.3, 0.6);
+
+/// Kills any `Actor` that touches it, except spikes only hurt the player
+#[derive(Component)]
+pub struct Hazard(pub HazardKind);
+
+/// Graphics of a hazard, thrown away and rebuilt when it changes size
+#[derive(Component)]
+struct HazardPart;
+
+pub struct HazardsPlugin;
+
+impl Plugin for HazardsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            actor_touch_hazard
+                .run_in_state(GameState::InGame)
+                .run_in_state(PauseState::Running),
+        )
+        .add_system(rebuild_hazard_parts);
+    }
+}
+
+pub fn spawn_hazard(
+    commands: &mut Commands,
+    hazard: HazardData,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) -> Entity {
+    let BoxData { tl, br } = hazard.area;
+    let half_extents = ((br - tl) / 2.).abs();
+    let center = (tl + br) / 2.;
+
+    commands
+        .spawn(Collider::cuboid(half_extents.x, half_extents.y))
+        .insert(Sensor)
+        // level geometry is static, actors are kinematic
+        .insert(ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_STATIC)
+        .insert(Hazard(hazard.kind))
+        .insert(InGameItem)
+        .insert(BoxTopLeft(tl))
+        .insert(BoxBottomRight(br))
+        .insert(TransformBundle::from_transform(
+            Transform::from_translation(center.extend(10.)),
+        ))
+        .insert(VisibilityBundle::default())
+        .with_children(|cb| {
+            spawn_hazard_parts(cb, hazard, texture_handles, meshes, materials);
+        })
+        .id()
+}
+
+fn spawn_hazard_parts(
+    cb: &mut ChildBuilder,
+    hazard: HazardData,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) {
+    let BoxData { tl, br } = hazard.area;
+    let w = (br.x - tl.x).abs();
+    let h = (br.y - tl.y).abs();
+
+    let (mesh, color) = match hazard.kind {
+        HazardKind::Spikes => (spikes_mesh(w, h), Color::WHITE),
+        HazardKind::KillZone => {
+            let mut mesh = Mesh::from(shape::Quad::new(Vec2::new(w, h)));
+            if let Some(VertexAttributeValues::Float32x2(uvs)) =
+                mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0)
+            {
+                for uv in uvs {
+                    uv[0] *= w / 5.;
+                    uv[1] *= h / 5.;
+                }
+            }
+            (mesh, KILL_ZONE_COLOR)
+        }
+    };
+
+    cb.spawn(MaterialMesh2dBundle {
+        mesh: meshes.add(mesh).into(),
+        material: materials.add(ColorMaterial {
+            color,
+            texture: Some(texture_handles.chalk_box_fill.clone().unwrap()),
+        }),
+        ..default()
+    })
+    .insert(HazardPart);
+}
+
+/// A row of triangles standing on the bottom of a `w` by `h` box, centered on the origin
+fn spikes_mesh(w: f32, h: f32) -> Mesh {
+    let count = (w / SPIKE_WIDTH).round().max(1.);
+    let spike_width = w / count;
+
+    let mut positions = vec![];
+    for i in 0..count as usize {
+        let left = -w / 2. + i as f32 * spike_width;
+        positions.push([left, -h / 2., 0.]);
+        positions.push([left + spike_width, -h / 2., 0.]);
+        positions.push([left + spike_width / 2., h / 2., 0.]);
+    }
+
+    // same texture scale as box fills
+    let uvs: Vec<[f32; 2]> = positions.iter().map(|p| [p[0] / 5., -p[1] / 5.]).collect();
+    let normals = vec![[0., 0., 1.]; positions.len()];
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh
+}
+
+#[allow(clippy::type_complexity)]
+fn rebuild_hazard_parts(
+    mut commands: Commands,
+    q_hazards: Query<
+        (
+            Entity,
+            &Hazard,
+            &BoxTopLeft,
+            &BoxBottomRight,
+            ChangeTrackers<BoxTopLeft>,
+            Option<&Children>,
+        ),
+        Or<(Changed<BoxTopLeft>, Changed<BoxBottomRight>)>,
+    >,
+    q_parts: Query<Entity, With<HazardPart>>,
+    texture_handles: Res<TextureHandles>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, hazard, tl, br, tl_tracker, children) in &q_hazards {
+        // spawn_hazard already built these
+        if tl_tracker.is_added() {
+            continue;
+        }
+
+        for &child in children.into_iter().flatten() {
+            if q_parts.contains(child) {
+                commands.entity(child).despawn_recursive();
+            }
+        }
+
+        let hazard = HazardData {
+            area: BoxData { tl: tl.0, br: br.0 },
+            kind: hazard.0,
+        };
+        commands.entity(entity).with_children(|cb| {
+            spawn_hazard_parts(cb, hazard, &texture_handles, &mut meshes, &mut materials);
+        });
+    }
+}
+
+fn actor_touch_hazard(
+    mut commands: Commands,
+    rapier_context: Res<RapierContext>,
+    q_hazards: Query<(Entity, &Hazard, &Transform)>,
+    q_actors: Query<Option<&Player>, (With<Actor>, Without<ActorDead>)>,
+    audio: Res<Audio>,
+    sound_collection: Res<SoundCollection>,
+    audio_volume: Res<AudioVolume>,
+) {
+    for (hazard, Hazard(kind), transform) in &q_hazards {
+        for (a, b, intersecting) in rapier_context.intersections_with(hazard) {
+            let actor = if a == hazard { b } else { a };
+            let Ok(player) = q_actors.get(actor) else {
+                continue;
+            };
+            if !intersecting {
+                continue;
+            }
+
+            if player.is_some() && *kind == HazardKind::Spikes {
+                commands.entity(actor).insert(PlayerHurt {
+                    from: transform.translation.truncate(),
+                });
+            } else {
+                audio.play_with_settings(
+                    sound_collection.kill.clone(),
+                    PlaybackSettings::ONCE.with_volume(audio_volume.0),
+                );
+                commands.entity(actor).insert(ActorDead);
+            }
+        }
+    }
+}
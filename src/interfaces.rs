@@ -5,6 +5,9 @@ use bevy::prelude::*;
 use iyes_loopless::prelude::*;
 
 use crate::end_screen::mute_bgm;
+use crate::level::start_run;
+use crate::level_editor::Playtest;
+use crate::level_loader::LEVEL_PATHS;
 use crate::states::GameState;
 use crate::states::PauseState;
 use crate::util::despawn_with;
@@ -28,7 +31,7 @@ struct MuteButton;
 
 // TODO next: death and retry
 #[derive(Component)]
-struct MenuButton;
+pub struct MenuButton;
 
 #[derive(Component)]
 struct PlayButton;
@@ -37,7 +40,16 @@ struct PlayButton;
 struct EditorButton;
 
 #[derive(Component)]
-struct QuitButton;
+struct LevelsButton;
+
+#[derive(Component)]
+struct LevelSelectItem;
+
+#[derive(Component)]
+struct LevelSelectButton(usize);
+
+#[derive(Component)]
+pub struct QuitButton;
 
 #[derive(Component)]
 struct PauseItem;
@@ -57,11 +69,17 @@ impl Plugin for UserInterfacesPlugin {
                 mute_bgm.run_if_resource_exists::<BackgroundMusic>(),
             )
             .add_exit_system(GameState::MainMenu, despawn_with::<MenuItem>)
+            // level select transitions
+            .add_enter_system(GameState::LevelSelect, setup_level_select)
+            .add_exit_system(GameState::LevelSelect, despawn_with::<LevelSelectItem>)
             // dead transitions
             .add_enter_system(GameState::Dead, setup_dead)
             .add_exit_system(GameState::Dead, despawn_with::<DeadItem>)
             // pause transitions
-            .add_enter_system(PauseState::Paused, setup_pause_menu)
+            .add_enter_system(
+                PauseState::Paused,
+                setup_pause_menu.run_not_in_state(GameState::LevelEditor),
+            )
             .add_exit_system(PauseState::Paused, despawn_with::<PauseItem>)
             // button systems
             .add_system(quit_button)
@@ -70,7 +88,9 @@ impl Plugin for UserInterfacesPlugin {
             .add_system(editor_button)
             .add_system(pause_resume_button.run_in_state(PauseState::Paused))
             .add_system(pause_mute_button)
-            .add_system(play_button.run_in_state(GameState::MainMenu));
+            .add_system(play_button.run_in_state(GameState::MainMenu))
+            .add_system(levels_button.run_in_state(GameState::MainMenu))
+            .add_system(level_select_button.run_in_state(GameState::LevelSelect));
     }
 }
 
@@ -142,6 +162,7 @@ fn menu_button(
     audio: Res<Audio>,
     sound_collection: Res<SoundCollection>,
     audio_volume: Res<AudioVolume>,
+    playtest: Option<Res<Playtest>>,
 ) {
     for interact in &button_query {
         match *interact {
@@ -152,7 +173,13 @@ fn menu_button(
                 );
 
                 commands.insert_resource(NextState(PauseState::Running));
-                commands.insert_resource(NextState(GameState::MainMenu));
+
+                // playtests go back to where they came from
+                if playtest.is_some() {
+                    commands.insert_resource(NextState(GameState::LevelEditor));
+                } else {
+                    commands.insert_resource(NextState(GameState::MainMenu));
+                }
             }
             Interaction::Hovered => {}
             Interaction::None => {}
@@ -222,33 +249,61 @@ fn setup_menu(mut commands: Commands, ui_font: Res<UiFont>) {
         .insert(MenuItem)
         .insert(PlayButton);
 
-    // commands
-    //     .spawn(ButtonBundle {
-    //         style: Style {
-    //             size: Size::new(Val::Px(150.0), Val::Px(65.0)),
-    //             // center button
-    //             margin: UiRect::all(Val::Auto),
-    //             // horizontally center child text
-    //             justify_content: JustifyContent::Center,
-    //             // vertically center child text
-    //             align_items: AlignItems::Center,
-    //             ..default()
-    //         },
-    //         background_color: Color::rgb(128., 0., 0.).into(),
-    //         ..default()
-    //     })
-    //     .with_children(|parent| {
-    //         parent.spawn(TextBundle::from_section(
-    //             "EDITOR",
-    //             TextStyle {
-    //                 font: ui_font.0.clone(),
-    //                 font_size: 40.0,
-    //                 color: Color::rgb(0.9, 0.9, 0.9),
-    //             },
-    //         ));
-    //     })
-    //     .insert(MenuItem)
-    //     .insert(EditorButton);
+    commands
+        .spawn(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(150.0), Val::Px(65.0)),
+                // center button
+                margin: UiRect::all(Val::Auto),
+                // horizontally center child text
+                justify_content: JustifyContent::Center,
+                // vertically center child text
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::rgb(128., 0., 0.).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "LEVELS",
+                TextStyle {
+                    font: ui_font.0.clone(),
+                    font_size: 40.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            ));
+        })
+        .insert(MenuItem)
+        .insert(LevelsButton);
+
+    commands
+        .spawn(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(150.0), Val::Px(65.0)),
+                // center button
+                margin: UiRect::all(Val::Auto),
+                // horizontally center child text
+                justify_content: JustifyContent::Center,
+                // vertically center child text
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::rgb(128., 0., 0.).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "EDITOR",
+                TextStyle {
+                    font: ui_font.0.clone(),
+                    font_size: 40.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            ));
+        })
+        .insert(MenuItem)
+        .insert(EditorButton);
 
     // quit button
     commands
@@ -294,6 +349,7 @@ fn play_button(
                     sound_collection.beep.clone(),
                     PlaybackSettings::ONCE.with_volume(audio_volume.0),
                 );
+                start_run(&mut commands, 0);
                 commands.insert_resource(NextState(GameState::IntroCutscene));
             }
             Interaction::Hovered => {}
@@ -302,6 +358,147 @@ fn play_button(
     }
 }
 
+fn levels_button(
+    mut commands: Commands,
+    button_query: Query<&Interaction, With<LevelsButton>>,
+    audio: Res<Audio>,
+    sound_collection: Res<SoundCollection>,
+    audio_volume: Res<AudioVolume>,
+) {
+    for interact in &button_query {
+        match *interact {
+            Interaction::Clicked => {
+                audio.play_with_settings(
+                    sound_collection.beep.clone(),
+                    PlaybackSettings::ONCE.with_volume(audio_volume.0),
+                );
+                commands.insert_resource(NextState(GameState::LevelSelect));
+            }
+            Interaction::Hovered => {}
+            Interaction::None => {}
+        }
+    }
+}
+
+fn setup_level_select(mut commands: Commands, ui_font: Res<UiFont>) {
+    // text
+    commands
+        .spawn(TextBundle {
+            text: Text::from_section(
+                "levels",
+                TextStyle {
+                    font: ui_font.0.clone(),
+                    font_size: 40.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            ),
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Px(65.0)),
+                // center button
+                margin: UiRect::all(Val::Auto),
+                // horizontally center child text
+                justify_content: JustifyContent::Center,
+                // vertically center child text
+                align_items: AlignItems::Center,
+
+                position: UiRect {
+                    left: Val::Px(50.),
+                    top: Val::Px(50.),
+                    ..default()
+                },
+
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(LevelSelectItem);
+
+    for level in 0..LEVEL_PATHS.len() {
+        commands
+            .spawn(ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(150.0), Val::Px(65.0)),
+                    // center button
+                    margin: UiRect::all(Val::Auto),
+                    // horizontally center child text
+                    justify_content: JustifyContent::Center,
+                    // vertically center child text
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgb(128., 0., 0.).into(),
+                ..default()
+            })
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    format!("LEVEL {}", level + 1),
+                    TextStyle {
+                        font: ui_font.0.clone(),
+                        font_size: 40.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                    },
+                ));
+            })
+            .insert(LevelSelectItem)
+            .insert(LevelSelectButton(level));
+    }
+
+    // back to the main menu
+    commands
+        .spawn(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(150.0), Val::Px(65.0)),
+                // center button
+                margin: UiRect::all(Val::Auto),
+                // horizontally center child text
+                justify_content: JustifyContent::Center,
+                // vertically center child text
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::rgb(128., 0., 0.).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "MENU",
+                TextStyle {
+                    font: ui_font.0.clone(),
+                    font_size: 40.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            ));
+        })
+        .insert(LevelSelectItem)
+        .insert(MenuButton);
+}
+
+fn level_select_button(
+    mut commands: Commands,
+    button_query: Query<(&Interaction, &LevelSelectButton)>,
+    audio: Res<Audio>,
+    sound_collection: Res<SoundCollection>,
+    audio_volume: Res<AudioVolume>,
+) {
+    for (interact, button) in &button_query {
+        match *interact {
+            Interaction::Clicked => {
+                audio.play_with_settings(
+                    sound_collection.beep.clone(),
+                    PlaybackSettings::ONCE.with_volume(audio_volume.0),
+                );
+
+                // skips the intro, the run starts from the picked level
+                start_run(&mut commands, button.0);
+                commands.insert_resource(NextState(GameState::InGame));
+            }
+            Interaction::Hovered => {}
+            Interaction::None => {}
+        }
+    }
+}
+
 fn editor_button(mut commands: Commands, button_query: Query<&Interaction, With<EditorButton>>) {
     for interact in &button_query {
         match *interact {
@@ -481,7 +678,11 @@ fn replay_button(
     }
 }
 
-fn setup_pause_menu(mut commands: Commands, ui_font: Res<UiFont>) {
+fn setup_pause_menu(
+    mut commands: Commands,
+    ui_font: Res<UiFont>,
+    playtest: Option<Res<Playtest>>,
+) {
     // text
     commands
         .spawn(TextBundle {
@@ -545,6 +746,8 @@ fn setup_pause_menu(mut commands: Commands, ui_font: Res<UiFont>) {
         .insert(ResumeButton);
 
     // menu button
+    let menu_text = if playtest.is_some() { "EDITOR" } else { "MENU" };
+
     commands
         .spawn(ButtonBundle {
             style: Style {
@@ -562,7 +765,7 @@ fn setup_pause_menu(mut commands: Commands, ui_font: Res<UiFont>) {
         })
         .with_children(|parent| {
             parent.spawn(TextBundle::from_section(
-                "MENU",
+                menu_text,
                 TextStyle {
                     font: ui_font.0.clone(),
                     font_size: 40.0,
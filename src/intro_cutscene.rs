@@ -100,6 +100,7 @@ const ZOOM_OUT_TIME: f32 = 0.5;
 const ZOOM_Y_OFFSET: f32 = -5.;
 const ZOOM_FACTOR: f32 = 0.5;
 
+#[allow(clippy::too_many_arguments)]
 fn start(
     mut commands: Commands,
 
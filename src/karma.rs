@@ -0,0 +1,135 @@
+use bevy::{prelude::*, utils::HashSet};
+use iyes_loopless::prelude::{ConditionHelpers, IntoConditionalSystem};
+
+use crate::{
+    end_screen::Endings,
+    level::{CurrentLevel, LevelEnemy},
+    player::Player,
+    states::{GameState, PauseState},
+};
+
+/// Something the player did that an ending might care about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KarmaEvent {
+    /// an enemy was dead by the end of its level
+    Kill,
+    /// an enemy was still alive at the end of its level
+    Spare,
+    Jump,
+    Death,
+    /// got past an enemy while it was still alive
+    EnemyPassed,
+    BossDefeated,
+    /// reached the end of his level with him still alive
+    BossSpared,
+}
+
+/// Everything the player has done this run, in order, with the run time each
+/// thing happened at. Kills, spares and the boss are only recorded once a level
+/// is finished, so going back to a checkpoint can't count them twice
+#[derive(Resource, Default)]
+pub struct Karma {
+    pub events: Vec<(f32, KarmaEvent)>,
+    /// seconds of play so far, not counting pauses
+    pub time: f32,
+    /// level and `LevelEnemy` index of every enemy passed, respawning puts
+    /// them back in front of the player
+    passed: HashSet<(usize, usize)>,
+}
+
+impl Karma {
+    pub fn record(&mut self, event: KarmaEvent) {
+        self.events.push((self.time, event));
+    }
+
+    pub fn count(&self, event: KarmaEvent) -> usize {
+        self.events.iter().filter(|(_, e)| *e == event).count()
+    }
+
+    pub fn happened(&self, event: KarmaEvent) -> bool {
+        self.events.iter().any(|(_, e)| *e == event)
+    }
+}
+
+/// An ending, the cutscene that plays it and whether the run earned it
+pub struct EndingRule {
+    pub ending: Endings,
+    pub state: GameState,
+    pub applies: fn(&Karma) -> bool,
+}
+
+/// The ending rules, tried in order until one applies. A new ending needs an
+/// `Endings`, a cutscene state and a rule here, above the normal ending
+pub const ENDING_RULES: [EndingRule; 3] = [
+    // nobody left standing, mr fuqheed included
+    EndingRule {
+        ending: Endings::Genocide,
+        state: GameState::GenocideEnding,
+        applies: |karma| {
+            karma.count(KarmaEvent::Spare) == 0 && !karma.happened(KarmaEvent::BossSpared)
+        },
+    },
+    // nobody hurt
+    EndingRule {
+        ending: Endings::Pacifist,
+        state: GameState::PacifistEnding,
+        applies: |karma| {
+            karma.count(KarmaEvent::Kill) == 0 && !karma.happened(KarmaEvent::BossDefeated)
+        },
+    },
+    EndingRule {
+        ending: Endings::Normal,
+        state: GameState::NormalEnding,
+        applies: |_| true,
+    },
+];
+
+/// The first ending the run so far has earned
+pub fn choose_ending(karma: &Karma) -> &'static EndingRule {
+    ENDING_RULES
+        .iter()
+        .find(|rule| (rule.applies)(karma))
+        .unwrap_or(&ENDING_RULES[ENDING_RULES.len() - 1])
+}
+
+pub struct KarmaPlugin;
+
+impl Plugin for KarmaPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Karma>()
+            .add_system(
+                count_run_time
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(PauseState::Running),
+            )
+            .add_system(
+                record_enemies_passed
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(PauseState::Running),
+            );
+    }
+}
+
+fn count_run_time(time: Res<Time>, mut karma: ResMut<Karma>) {
+    karma.time += time.delta_seconds();
+}
+
+// levels go left to right, so being further right than an enemy is past it
+fn record_enemies_passed(
+    q_player: Query<&Transform, With<Player>>,
+    q_enemies: Query<(&Transform, &LevelEnemy)>,
+    current_level: Res<CurrentLevel>,
+    mut karma: ResMut<Karma>,
+) {
+    let Ok(player_transform) = q_player.get_single() else {
+        return;
+    };
+
+    for (transform, LevelEnemy(index)) in &q_enemies {
+        if player_transform.translation.x > transform.translation.x
+            && karma.passed.insert((current_level.0, *index))
+        {
+            karma.record(KarmaEvent::EnemyPassed);
+        }
+    }
+}
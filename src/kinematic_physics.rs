@@ -1,23 +1,57 @@
-use bevy::prelude::*;
+use std::{f32::consts::PI, time::Duration};
+
+use bevy::{ecs::system::AsSystemLabel, prelude::*, transform::TransformSystem};
 use bevy_rapier2d::prelude::*;
-use iyes_loopless::state::CurrentState;
+use iyes_loopless::{
+    fixedtimestep::{app::AppLooplessFixedTimestepExt, FixedTimesteps},
+    prelude::{ConditionHelpers, IntoConditionalSystem},
+    state::CurrentState,
+};
 
 use crate::{
-    enemy::{Enemy, EnemyMover, KillEnemyHitbox, KillPlayerHitbox},
-    level::Wall,
-    player::Player,
-    states::PauseState,
-    ActorDead, SoundCollection, SystemOrderLabel, interfaces::AudioVolume,
+    enemy::{
+        Enemy, EnemyChaser, EnemyFlyer, EnemyHealth, EnemyJumper, EnemyMover, EnemyStunned,
+        KillEnemyHitbox, KillPlayerHitbox,
+    },
+    interfaces::AudioVolume,
+    level::{OneWayPlatform, Wall, ONE_WAY_PLATFORM_GROUP},
+    movement_config::MovementConfig,
+    player::{Player, PlayerHurt, WallContact, PLAYER_RADIUS},
+    states::{GameState, PauseState},
+    ActorDead, SoundCollection, SystemOrderLabel,
 };
 
-// physical constants
-pub const CC_GRAVITY: f32 = 0.1;
-pub const CC_FRICTION_COEFFICIENT: f32 = 1.2;
-
-pub const PLAYER_WALK_SPEED: f32 = 0.3;
-
-pub const ENEMY_WALK_ACCEL: f32 = 0.05;
-pub const ENEMY_WALK_SPEED: f32 = 0.05;
+// everything below is tuned per tick, at this rate
+pub const PHYSICS_TICK_RATE: f64 = 60.;
+pub const PHYSICS_TIMESTEP: &str = "physics";
+
+// substages of the physics timestep, commands are applied in between
+const RESTORE_SUBSTAGE: usize = 0;
+pub const MOVEMENT_SUBSTAGE: usize = 1;
+const SYNC_SUBSTAGE: usize = 2;
+const STEP_SUBSTAGE: usize = 3;
+const WRITEBACK_SUBSTAGE: usize = 4;
+const RECORD_SUBSTAGE: usize = 5;
+
+// steepest slope characters can walk up. Anything gentler doesn't slide them
+// back down either, so they can stand still on it
+const MAX_SLOPE_ANGLE: f32 = 50. * PI / 180.;
+
+// how far characters get pulled down onto the ground after moving, so walking
+// down a slope follows it instead of hopping off. Covers a tick of walking down
+// the steepest slope
+const SNAP_TO_GROUND: f32 = 0.5;
+
+/// Controller settings shared by everything that walks around
+pub fn character_controller() -> KinematicCharacterController {
+    KinematicCharacterController {
+        apply_impulse_to_dynamic_bodies: true,
+        max_slope_climb_angle: MAX_SLOPE_ANGLE,
+        min_slope_slide_angle: MAX_SLOPE_ANGLE,
+        snap_to_ground: Some(CharacterLength::Absolute(SNAP_TO_GROUND)),
+        ..default()
+    }
+}
 
 #[derive(Component)]
 pub struct KinematicGravity;
@@ -28,13 +62,88 @@ pub struct CCAcceleration(pub Vec2);
 #[derive(Component)]
 pub struct CCVelocity(pub Vec2);
 
+/// Where a kinematic body was after the last two physics ticks. It's drawn
+/// somewhere in between, so movement stays smooth when the frame rate and tick
+/// rate don't line up
+#[derive(Component, Default)]
+pub struct PhysicsInterpolation {
+    previous: Option<Vec3>,
+    current: Option<Vec3>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemLabel)]
+enum EnemyMovementLabel {
+    Fly,
+}
+
 pub struct KinematicPhysics;
 
 impl Plugin for KinematicPhysics {
     fn build(&self, app: &mut App) {
-        app // kinematic systems
-            .add_system(kinematic_clear_acceleration.before(SystemOrderLabel::Input))
-            .add_system_set(
+        let tick = 1. / PHYSICS_TICK_RATE;
+
+        app.insert_resource(RapierConfiguration {
+            timestep_mode: TimestepMode::Fixed {
+                dt: tick as f32,
+                substeps: 1,
+            },
+            ..default()
+        })
+        .add_fixed_timestep(Duration::from_secs_f64(tick), PHYSICS_TIMESTEP);
+
+        for _ in RESTORE_SUBSTAGE..RECORD_SUBSTAGE {
+            app.add_fixed_timestep_child_stage(PHYSICS_TIMESTEP);
+        }
+
+        app // interpolation
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                RESTORE_SUBSTAGE,
+                restore_physics_translation,
+            )
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                RECORD_SUBSTAGE,
+                record_physics_translation,
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                interpolate_physics_translation
+                    .label(SystemOrderLabel::Interpolation)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            // rapier, stepped once per tick rather than once per frame
+            .add_fixed_timestep_system_set(
+                PHYSICS_TIMESTEP,
+                SYNC_SUBSTAGE,
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::SyncBackend),
+            )
+            .add_fixed_timestep_system_set(
+                PHYSICS_TIMESTEP,
+                STEP_SUBSTAGE,
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::StepSimulation),
+            )
+            .add_fixed_timestep_system_set(
+                PHYSICS_TIMESTEP,
+                WRITEBACK_SUBSTAGE,
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::Writeback),
+            )
+            .add_stage_before(
+                CoreStage::Last,
+                PhysicsStages::DetectDespawn,
+                SystemStage::parallel().with_system_set(
+                    RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::DetectDespawn),
+                ),
+            )
+            // kinematic systems
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                MOVEMENT_SUBSTAGE,
+                kinematic_clear_acceleration.before(SystemOrderLabel::Input),
+            )
+            .add_fixed_timestep_system_set(
+                PHYSICS_TIMESTEP,
+                MOVEMENT_SUBSTAGE,
                 SystemSet::new()
                     .label(SystemOrderLabel::Movement)
                     .with_system(kinematic_gravity)
@@ -55,15 +164,45 @@ impl Plugin for KinematicPhysics {
                             .label(SystemOrderLabel::Collisions)
                             .after(kinematic_gravity),
                     )
+                    // enemy timers mustn't run on while paused. Conditional systems
+                    // can't be ordered by function name, only by label
+                    .with_system(
+                        enemy_recover
+                            .run_in_state(GameState::InGame)
+                            .run_in_state(PauseState::Running)
+                            .before(player_kill_enemy.as_system_label()),
+                    )
                     .with_system(move_enemies.after(kinematic_gravity))
+                    .with_system(
+                        enemy_patrol
+                            .after(enemy_bounce_off_obstacle)
+                            .before(move_enemies),
+                    )
                     .with_system(
                         enemy_bounce_off_obstacle
                             .label(SystemOrderLabel::Collisions)
                             .after(kinematic_gravity),
                     )
+                    .with_system(enemy_chase.after(kinematic_gravity))
+                    .with_system(
+                        enemy_jump
+                            .run_in_state(GameState::InGame)
+                            .run_in_state(PauseState::Running)
+                            .after(kinematic_gravity.as_system_label())
+                            .before(kinematic_set_velocity.as_system_label()),
+                    )
+                    .with_system(
+                        enemy_fly
+                            .run_in_state(GameState::InGame)
+                            .run_in_state(PauseState::Running)
+                            .label(EnemyMovementLabel::Fly)
+                            .after(kinematic_set_velocity.as_system_label())
+                            .before(kinematic_apply_velocity.as_system_label()),
+                    )
                     .with_system(
                         kinematic_set_velocity
                             .after(move_enemies)
+                            .after(enemy_chase)
                             .after(kinematic_gravity)
                             .after(SystemOrderLabel::Collisions)
                             .after(kinematic_apply_friction),
@@ -83,19 +222,87 @@ impl Plugin for KinematicPhysics {
                             .before(kinematic_apply_velocity),
                     )
                     .with_system(player_wall_raycast.before(kinematic_set_velocity))
+                    .with_system(
+                        player_wall_slide
+                            .after(kinematic_set_velocity)
+                            .after(player_wall_raycast)
+                            .before(kinematic_apply_velocity),
+                    )
                     .with_system(player_roof_raycast.before(kinematic_set_velocity))
+                    .with_system(
+                        kinematic_one_way_platforms
+                            .after(kinematic_set_velocity)
+                            .after(player_wall_slide)
+                            // needs the final velocity to know if it's going up
+                            .after(player_max_speed)
+                            .after(enemy_max_speed)
+                            .after(EnemyMovementLabel::Fly)
+                            .before(kinematic_apply_velocity),
+                    )
                     .with_system(kinematic_apply_velocity.after(kinematic_set_velocity))
                     .after(SystemOrderLabel::Input),
             );
     }
 }
 
+/// Length of a physics tick in seconds
+pub fn physics_dt(timesteps: &FixedTimesteps) -> f32 {
+    timesteps
+        .get(PHYSICS_TIMESTEP)
+        .map_or((1. / PHYSICS_TICK_RATE) as f32, |info| {
+            info.timestep().as_secs_f32()
+        })
+}
+
+// physics only ever sees where things really are, not where they're drawn
+fn restore_physics_translation(mut query: Query<(&mut Transform, &PhysicsInterpolation)>) {
+    for (mut transform, interpolation) in &mut query {
+        if let Some(current) = interpolation.current {
+            transform.translation = current;
+        }
+    }
+}
+
+fn record_physics_translation(mut query: Query<(&Transform, &mut PhysicsInterpolation)>) {
+    for (transform, mut interpolation) in &mut query {
+        interpolation.previous = interpolation.current.or(Some(transform.translation));
+        interpolation.current = Some(transform.translation);
+    }
+}
+
+fn interpolate_physics_translation(
+    mut query: Query<(&mut Transform, &PhysicsInterpolation)>,
+    timesteps: Res<FixedTimesteps>,
+) {
+    let Some(info) = timesteps.get(PHYSICS_TIMESTEP) else {
+        return;
+    };
+    let overstep = info.overstep().min(1.) as f32;
+
+    for (mut transform, interpolation) in &mut query {
+        if let (Some(previous), Some(current)) = (interpolation.previous, interpolation.current) {
+            transform.translation = previous.lerp(current, overstep);
+        }
+    }
+}
+
 fn player_wall_raycast(
     rapier_context: Res<RapierContext>,
-    mut q_player: Query<(Entity, &Transform, &mut CCVelocity, &mut CCAcceleration), With<Player>>,
-    q_walls: Query<Entity, With<Wall>>,
+    mut q_player: Query<
+        (
+            Entity,
+            &Transform,
+            &mut CCVelocity,
+            &mut CCAcceleration,
+            &mut WallContact,
+        ),
+        With<Player>,
+    >,
+    q_walls: Query<Entity, (With<Wall>, Without<OneWayPlatform>)>,
 ) {
-    if let Ok((player, player_transform, mut vel, mut acc)) = q_player.get_single_mut() {
+    if let Ok((player, player_transform, mut vel, mut acc, mut wall)) = q_player.get_single_mut() {
+        *wall = WallContact::default();
+
         let max_toi = 1.0;
         let ray_origin = Vec2::new(
             player_transform.translation.x,
@@ -108,13 +315,16 @@ fn player_wall_raycast(
         let mut callback = |entity, intersection: RayIntersection| {
             if let Ok(_wall) = q_walls.get(entity) {
                 let hit_normal = intersection.normal;
+                if hit_normal.x != 0. {
+                    wall.normal = Some(hit_normal.x.signum());
+                }
 
-                if (vel.0.x > 0. || acc.0.x > 0.) && hit_normal.x < 0. {
-                    vel.0.x = 0.;
-                    acc.0.x = 0.;
-                } else if (vel.0.x < 0. || acc.0.x < 0.) && hit_normal.x > 0. {
+                if ((vel.0.x > 0. || acc.0.x > 0.) && hit_normal.x < 0.)
+                    || ((vel.0.x < 0. || acc.0.x < 0.) && hit_normal.x > 0.)
+                {
                     vel.0.x = 0.;
                     acc.0.x = 0.;
+                    wall.pressing = true;
                 }
             }
 
@@ -141,10 +351,21 @@ fn player_wall_raycast(
     }
 }
 
+fn player_wall_slide(
+    mut query: Query<(&mut CCVelocity, &WallContact, &KinematicCharacterControllerOutput), With<Player>>,
+    config: Res<MovementConfig>,
+) {
+    for (mut vel, wall, output) in &mut query {
+        if wall.pressing && !output.grounded {
+            vel.0.y = vel.0.y.max(-config.player_wall_slide_speed);
+        }
+    }
+}
+
 fn player_roof_raycast(
     rapier_context: Res<RapierContext>,
     mut q_player: Query<(Entity, &Transform, &mut CCVelocity, &mut CCAcceleration), With<Player>>,
-    q_walls: Query<Entity, With<Wall>>,
+    q_walls: Query<Entity, (With<Wall>, Without<OneWayPlatform>)>,
 ) {
     if let Ok((player, player_transform, mut vel, mut acc)) = q_player.get_single_mut() {
         let max_toi = 1.0;
@@ -174,25 +395,87 @@ fn player_roof_raycast(
     }
 }
 
+// how long an enemy that survives a stomp can't be hurt again, and how long
+// it's stunned for
+const ENEMY_INVULNERABLE_TIME: f32 = 0.5;
+const ENEMY_STUN_TIME: f32 = 1.;
+
+// velocity a surviving enemy is knocked away from the player with
+const ENEMY_KNOCKBACK: Vec2 = Vec2::new(0.4, 0.5);
+
 /* Read the character controller collisions stored in the character controller’s output. */
+#[allow(clippy::type_complexity)]
 fn player_kill_enemy(
     mut commands: Commands,
-    mut q_player: Query<(&KinematicCharacterControllerOutput, &mut CCAcceleration), With<Player>>,
+    mut q_player: Query<
+        (
+            &Transform,
+            &KinematicCharacterControllerOutput,
+            &mut CCAcceleration,
+        ),
+        With<Player>,
+    >,
     q_attackboxes: Query<(&Parent, Entity), With<KillEnemyHitbox>>,
+    mut q_enemies: Query<
+        (&Transform, &mut CCVelocity, &mut EnemyHealth),
+        (With<Enemy>, Without<Player>),
+    >,
     audio: Res<Audio>,
     sound_collection: Res<SoundCollection>,
     audio_volume: Res<AudioVolume>,
 ) {
-    for (output, mut acc) in q_player.iter_mut() {
+    for (player_transform, output, mut acc) in q_player.iter_mut() {
         for collision in &output.collisions {
             if let Ok((parent, _hitbox)) = q_attackboxes.get(collision.entity) {
-                // kill enemy
-                commands.entity(parent.get()).insert(ActorDead);
-
-                audio.play_with_settings(sound_collection.kill.clone(), PlaybackSettings::ONCE.with_volume(audio_volume.0));
+                // the boss takes more than one stomp, see `boss::player_stomp_boss`
+                let Ok((transform, mut vel, mut health)) = q_enemies.get_mut(parent.get()) else {
+                    continue;
+                };
 
                 // bounce
                 acc.0.y += 0.4;
+
+                if health.invulnerable > 0. {
+                    continue;
+                }
+
+                audio.play_with_settings(sound_collection.kill.clone(), PlaybackSettings::ONCE.with_volume(audio_volume.0));
+
+                health.health = health.health.saturating_sub(1);
+                if health.health == 0 {
+                    // kill enemy
+                    commands.entity(parent.get()).insert(ActorDead);
+                    continue;
+                }
+
+                // knock it away and leave it dazed
+                let away = (transform.translation.x - player_transform.translation.x).signum();
+                vel.0 = Vec2::new(away * ENEMY_KNOCKBACK.x, ENEMY_KNOCKBACK.y);
+                health.invulnerable = ENEMY_INVULNERABLE_TIME;
+                commands.entity(parent.get()).insert(EnemyStunned {
+                    remaining: ENEMY_STUN_TIME,
+                });
+            }
+        }
+    }
+}
+
+fn enemy_recover(
+    mut commands: Commands,
+    timesteps: Res<FixedTimesteps>,
+    mut q_enemies: Query<(Entity, &mut EnemyHealth, Option<&mut EnemyStunned>)>,
+) {
+    let dt = physics_dt(&timesteps);
+
+    for (entity, mut health, stunned) in &mut q_enemies {
+        if health.invulnerable > 0. {
+            health.invulnerable = (health.invulnerable - dt).max(0.);
+        }
+
+        if let Some(mut stunned) = stunned {
+            stunned.remaining -= dt;
+            if stunned.remaining <= 0. {
+                commands.entity(entity).remove::<EnemyStunned>();
             }
         }
     }
@@ -201,27 +484,36 @@ fn player_kill_enemy(
 fn player_enemy_collision(
     mut commands: Commands,
     mut q_player: Query<(Entity, &KinematicCharacterControllerOutput), With<Player>>,
-    q_killboxes: Query<Entity, With<KillPlayerHitbox>>,
+    // dazed enemies are harmless until they come round
+    q_killboxes: Query<&Transform, (With<KillPlayerHitbox>, Without<EnemyStunned>)>,
 ) {
     for (player, output) in q_player.iter_mut() {
         for collision in &output.collisions {
-            if let Ok(_killbox) = q_killboxes.get(collision.entity) {
-                // kill player
-                commands.entity(player).insert(ActorDead);
+            if let Ok(killbox) = q_killboxes.get(collision.entity) {
+                // hurt player
+                commands.entity(player).insert(PlayerHurt {
+                    from: killbox.translation.truncate(),
+                });
             }
         }
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn enemy_player_collision(
     mut commands: Commands,
     q_player: Query<Entity, With<Player>>,
-    q_enemies: Query<&KinematicCharacterControllerOutput, With<KillPlayerHitbox>>,
+    q_enemies: Query<
+        (&Transform, &KinematicCharacterControllerOutput),
+        (With<KillPlayerHitbox>, Without<EnemyStunned>),
+    >,
 ) {
-    for output in q_enemies.iter() {
+    for (transform, output) in q_enemies.iter() {
         for collision in &output.collisions {
             if let Ok(player) = q_player.get(collision.entity) {
-                commands.entity(player).insert(ActorDead);
+                commands.entity(player).insert(PlayerHurt {
+                    from: transform.translation.truncate(),
+                });
             }
         }
     }
@@ -244,9 +536,164 @@ fn enemy_bounce_off_obstacle(
     }
 }
 
-fn move_enemies(mut q: Query<(&mut CCAcceleration, &EnemyMover), With<Enemy>>) {
+// drops deeper than this below an enemy's feet are ledges. Deep enough that
+// walking down the steepest slope doesn't count
+const LEDGE_DEPTH: f32 = 1.;
+
+fn enemy_patrol(
+    rapier_context: Res<RapierContext>,
+    mut q_enemies: Query<(
+        Entity,
+        &Transform,
+        &KinematicCharacterControllerOutput,
+        &mut EnemyMover,
+    )>,
+) {
+    for (entity, transform, output, mut mover) in &mut q_enemies {
+        let x = transform.translation.x;
+
+        if mover.patrol_left.is_some_and(|left| x < mover.home - left) {
+            mover.dir = 1.;
+        } else if mover
+            .patrol_right
+            .is_some_and(|right| x > mover.home + right)
+        {
+            mover.dir = -1.;
+        }
+
+        // only the ground it's on has ledges, not wherever it's falling to
+        if !mover.turn_at_ledges || !output.grounded {
+            continue;
+        }
+
+        // look down from just in front
+        let ray_origin = transform.translation.truncate() + Vec2::X * mover.dir * PLAYER_RADIUS;
+        let ground = rapier_context.cast_ray(
+            ray_origin,
+            Vec2::NEG_Y,
+            PLAYER_RADIUS + LEDGE_DEPTH,
+            true,
+            QueryFilter::default()
+                .exclude_sensors()
+                .exclude_rigid_body(entity),
+        );
+
+        if ground.is_none() {
+            mover.dir = -mover.dir;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn move_enemies(
+    mut q: Query<(&mut CCAcceleration, &EnemyMover), (With<Enemy>, Without<EnemyStunned>)>,
+    config: Res<MovementConfig>,
+) {
     for (mut acc, mover) in q.iter_mut() {
-        acc.0.x += ENEMY_WALK_ACCEL * mover.dir;
+        acc.0.x += config.enemy_walk_accel * mover.dir;
+    }
+}
+
+/// Whether `target` is within `range` of `viewer` with nothing in between.
+/// Walls and other characters are in the way, one-way platforms and ledge
+/// bumpers aren't
+pub fn in_sight(
+    rapier_context: &RapierContext,
+    viewer: Entity,
+    from: Vec2,
+    target: Entity,
+    to: Vec2,
+    range: f32,
+) -> bool {
+    let towards = to - from;
+    if towards.length() > range {
+        return false;
+    }
+
+    rapier_context
+        .cast_ray(
+            from,
+            towards.normalize_or_zero(),
+            range,
+            true,
+            QueryFilter::default()
+                .exclude_sensors()
+                .exclude_rigid_body(viewer)
+                .groups(CollisionGroups::new(Group::GROUP_2, Group::GROUP_2)),
+        )
+        .is_some_and(|(hit, _)| hit == target)
+}
+
+// how far away chasers notice the player
+const CHASE_RANGE: f32 = 12.;
+
+#[allow(clippy::type_complexity)]
+fn enemy_chase(
+    rapier_context: Res<RapierContext>,
+    q_player: Query<(Entity, &Transform), With<Player>>,
+    mut q_chasers: Query<
+        (Entity, &Transform, &mut CCAcceleration),
+        (With<EnemyChaser>, Without<EnemyStunned>),
+    >,
+    config: Res<MovementConfig>,
+) {
+    let Ok((player, player_transform)) = q_player.get_single() else {
+        return;
+    };
+
+    for (entity, transform, mut acc) in &mut q_chasers {
+        let (from, to) = (
+            transform.translation.truncate(),
+            player_transform.translation.truncate(),
+        );
+
+        if in_sight(&rapier_context, entity, from, player, to, CHASE_RANGE) {
+            acc.0.x += config.enemy_walk_accel * (to.x - from.x).signum();
+        }
+    }
+}
+
+fn enemy_jump(
+    timesteps: Res<FixedTimesteps>,
+    mut q_jumpers: Query<
+        (
+            &mut CCVelocity,
+            &mut EnemyJumper,
+            &KinematicCharacterControllerOutput,
+        ),
+        Without<EnemyStunned>,
+    >,
+    config: Res<MovementConfig>,
+) {
+    let dt = physics_dt(&timesteps);
+
+    for (mut vel, mut jumper, output) in &mut q_jumpers {
+        jumper.since_jump += dt;
+
+        if output.grounded && jumper.since_jump >= config.enemy_jump_interval {
+            vel.0.y = config.enemy_jump_speed;
+            jumper.since_jump = 0.;
+        }
+    }
+}
+
+// how high and how often flyers bob
+const FLYER_AMPLITUDE: f32 = 1.5;
+const FLYER_PERIOD: f32 = 3.;
+
+fn enemy_fly(
+    timesteps: Res<FixedTimesteps>,
+    mut q_flyers: Query<(&Transform, &mut CCVelocity, &mut EnemyFlyer)>,
+) {
+    let dt = physics_dt(&timesteps);
+
+    for (transform, mut vel, mut flyer) in &mut q_flyers {
+        flyer.time += dt;
+        let wave = (2. * PI * flyer.time / FLYER_PERIOD).sin();
+
+        // head straight for the wave each tick, so being blocked for a while
+        // doesn't knock it off course
+        vel.0.y = flyer.base_y + FLYER_AMPLITUDE * wave - transform.translation.y;
     }
 }
 
@@ -256,22 +703,16 @@ fn kinematic_gravity(
             &mut CCAcceleration,
             &mut CCVelocity,
             &KinematicCharacterControllerOutput,
-            Option<&Player>,
         ),
         With<KinematicGravity>,
     >,
     pause_state: Res<CurrentState<PauseState>>,
+    config: Res<MovementConfig>,
 ) {
-    for (mut acc, mut vel, output, player) in &mut query {
+    for (mut acc, mut vel, output) in &mut query {
         if pause_state.0 == PauseState::Running {
             if !output.grounded {
-                if let Some(player) = player {
-                    if player.can_jump.finished() {
-                        acc.0.y -= CC_GRAVITY;
-                    }
-                } else {
-                    acc.0.y -= CC_GRAVITY;
-                }
+                acc.0.y -= config.gravity;
             }
 
             if output.grounded && vel.0.y < 0. {
@@ -282,9 +723,9 @@ fn kinematic_gravity(
     }
 }
 
-fn kinematic_apply_friction(mut query: Query<&mut CCVelocity>) {
+fn kinematic_apply_friction(mut query: Query<&mut CCVelocity>, config: Res<MovementConfig>) {
     for mut vel in &mut query {
-        vel.0.x /= CC_FRICTION_COEFFICIENT;
+        vel.0.x /= config.friction;
     }
 }
 
@@ -311,26 +752,65 @@ fn kinematic_apply_velocity(
     }
 }
 
-fn player_max_speed(mut query: Query<&mut CCVelocity, With<Player>>) {
-    for mut vel in &mut query {
-        if vel.0.x > PLAYER_WALK_SPEED {
-            vel.0.x = PLAYER_WALK_SPEED;
-        }
+// passing through means leaving the platform group out of the controller's
+// filter: on the way up, while still inside one, or while dropping down
+#[allow(clippy::type_complexity)]
+fn kinematic_one_way_platforms(
+    rapier_context: Res<RapierContext>,
+    mut query: Query<(
+        Entity,
+        &Transform,
+        &Collider,
+        &CCVelocity,
+        &mut KinematicCharacterController,
+        Option<&Player>,
+    )>,
+    q_platforms: Query<(), With<OneWayPlatform>>,
+) {
+    for (entity, transform, collider, vel, mut controller, player) in &mut query {
+        let is_platform = |e| q_platforms.contains(e);
+        let inside = rapier_context
+            .intersection_with_shape(
+                transform.translation.truncate(),
+                0.,
+                collider,
+                QueryFilter::default()
+                    .exclude_collider(entity)
+                    .predicate(&is_platform),
+            )
+            .is_some();
+        let dropping = player.is_some_and(|player| player.dropping);
+
+        let mut groups = controller.filter_groups.unwrap_or_default();
+        groups.filters.set(
+            ONE_WAY_PLATFORM_GROUP,
+            !(vel.0.y > 0. || inside || dropping),
+        );
 
-        if vel.0.x < -PLAYER_WALK_SPEED {
-            vel.0.x = -PLAYER_WALK_SPEED;
+        if controller.filter_groups != Some(groups) {
+            controller.filter_groups = Some(groups);
         }
     }
 }
 
-fn enemy_max_speed(mut query: Query<&mut CCVelocity, With<Enemy>>) {
+fn player_max_speed(mut query: Query<&mut CCVelocity, With<Player>>, config: Res<MovementConfig>) {
     for mut vel in &mut query {
-        if vel.0.x > ENEMY_WALK_SPEED {
-            vel.0.x = ENEMY_WALK_SPEED;
-        }
+        vel.0.x = vel.0.x.clamp(-config.player_walk_speed, config.player_walk_speed);
+    }
+}
 
-        if vel.0.x < -ENEMY_WALK_SPEED {
-            vel.0.x = -ENEMY_WALK_SPEED;
-        }
+#[allow(clippy::type_complexity)]
+fn enemy_max_speed(
+    mut query: Query<(&mut CCVelocity, Option<&EnemyChaser>), (With<Enemy>, Without<EnemyStunned>)>,
+    config: Res<MovementConfig>,
+) {
+    // stunned enemies are left to fly off as fast as they were knocked
+    for (mut vel, chaser) in &mut query {
+        let max_speed = if chaser.is_some() {
+            config.enemy_chase_speed
+        } else {
+            config.enemy_walk_speed
+        };
+        vel.0.x = vel.0.x.clamp(-max_speed, max_speed);
     }
 }
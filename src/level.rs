@@ -5,41 +5,112 @@ use bevy_rapier2d::prelude::*;
 use iyes_loopless::prelude::{AppLooplessStateExt, ConditionHelpers, IntoConditionalSystem};
 
 use crate::{
+    boss::{spawn_boss, BossResult},
     enemy::{spawn_enemy, Enemy},
-    player::spawn_player,
+    hazards::spawn_hazard,
+    interfaces::AudioVolume,
+    karma::{Karma, KarmaEvent},
+    level_editor::Playtest,
+    level_loader::{BoxData, LevelData, LevelHandles, DEFAULT_DEATH_PLANE, LEVEL_PATHS},
+    platforms::{create_crumbling_platform, create_moving_platform},
+    player::{spawn_player, Lives, PlayerHealth, PLAYER_INVULNERABLE_TIME, PLAYER_MAX_HEALTH},
+    slopes::spawn_slope,
     states::{GameState, PauseState},
-    util::despawn_with,
-    Actor, ActorDead, InGameItem, SoundCollection, TextureHandles, DEATHPLANE, interfaces::AudioVolume,
+    util::{despawn_with, remove_resource},
+    Actor, ActorDead, InGameItem, SoundCollection, TextureHandles, UiFont,
 };
 pub struct LevelPlugin;
 
 impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
-        app
+        app.init_resource::<CurrentLevel>()
+            .init_resource::<DeathPlane>()
             // ingame transitions
             .add_enter_system(GameState::InGame, setup_level)
             .add_enter_system(GameState::InGame, setup_ingame_ui.after(setup_level))
             .add_exit_system(GameState::InGame, despawn_with::<InGameItem>)
+            .add_exit_system(GameState::InGame, remove_resource::<LevelPending>)
+            // the level asset can still be loading when the game starts
+            .add_system(
+                setup_level
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<LevelPending>(),
+            )
             .add_system(
                 actor_fall_out
                     .run_in_state(GameState::InGame)
                     .run_in_state(PauseState::Running),
             )
-            .add_system(update_respect_meter.run_in_state(GameState::InGame));
+            .add_system(update_respect_meter.run_in_state(GameState::InGame))
+            .add_system(update_player_hud.run_in_state(GameState::InGame))
+            .add_system(rebuild_box_parts);
     }
 }
 
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// on to the next level in `LEVEL_PATHS`
+    NextLevel,
+    /// end of the run, pick an ending cutscene
+    Ending,
+}
+
+/// Index into `LEVEL_PATHS` of the level being played
+#[derive(Resource, Default)]
+pub struct CurrentLevel(pub usize);
+
+/// Start a fresh run from the given level
+pub fn start_run(commands: &mut Commands, level: usize) {
+    commands.insert_resource(CurrentLevel(level));
+    commands.insert_resource(Karma::default());
+    commands.insert_resource(Lives::default());
+    commands.insert_resource(BossResult::default());
+    commands.remove_resource::<ReachedCheckpoint>();
+}
+
+/// Actors below this height are dead, set from the level being played
+#[derive(Resource)]
+pub struct DeathPlane(pub f32);
+
+impl Default for DeathPlane {
+    fn default() -> Self {
+        Self(DEFAULT_DEATH_PLANE)
+    }
+}
+
+/// Respawn point, index into the level's `checkpoints`
 #[derive(Component)]
-pub struct Trigger;
+pub struct Checkpoint(pub usize);
+
+/// Index into the level's `enemies`, so a checkpoint can tell which are dead
+#[derive(Component)]
+pub struct LevelEnemy(pub usize);
+
+/// The last checkpoint touched in the current level, and the enemies still
+/// alive when it was. Dying puts things back the way they were
+#[derive(Resource)]
+pub struct ReachedCheckpoint {
+    pub index: usize,
+    pub position: Vec2,
+    pub alive_enemies: Vec<usize>,
+}
 
 #[derive(Component)]
 pub struct Wall;
 
+/// A `Wall` that can be jumped up through and dropped down through
+#[derive(Component)]
+pub struct OneWayPlatform;
+
+/// Collision group only one-way platforms are in, so character controllers can
+/// leave it out of their filter to pass through them
+pub const ONE_WAY_PLATFORM_GROUP: Group = Group::GROUP_3;
+
 #[derive(Component)]
-struct BoxTopLeft(Vec2);
+pub struct BoxTopLeft(pub Vec2);
 
 #[derive(Component)]
-struct BoxBottomRight(Vec2);
+pub struct BoxBottomRight(pub Vec2);
 
 #[derive(Bundle)]
 struct BoxBundle {
@@ -68,6 +139,17 @@ impl Default for BoxBundle {
     }
 }
 
+/// Top left and bottom right corners of a box, worked out from its collider
+pub fn box_corners(collider: &Collider, transform: &Transform) -> (Vec2, Vec2) {
+    let he: Vec2 = collider.as_cuboid().unwrap().half_extents();
+    let center = transform.translation.truncate();
+
+    (
+        center + Vec2::new(-he.x, he.y),
+        center + Vec2::new(he.x, -he.y),
+    )
+}
+
 //helper
 // TODO overload for Pos/Size style, to learn overloading
 pub fn create_box(
@@ -77,16 +159,78 @@ pub fn create_box(
     texture_handles: &Res<TextureHandles>,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<ColorMaterial>>,
+) -> Entity {
+    spawn_box(commands, tl, br, false, texture_handles, meshes, materials)
+}
+
+pub fn create_platform(
+    commands: &mut Commands,
+    tl: Vec2,
+    br: Vec2,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) -> Entity {
+    let e = spawn_box(commands, tl, br, true, texture_handles, meshes, materials);
+    commands
+        .entity(e)
+        .insert(OneWayPlatform)
+        .insert(CollisionGroups::new(ONE_WAY_PLATFORM_GROUP, Group::ALL));
+    e
+}
+
+fn spawn_box(
+    commands: &mut Commands,
+    tl: Vec2,
+    br: Vec2,
+    one_way: bool,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
 ) -> Entity {
     let w = (br.x - tl.x).abs();
     let h = (br.y - tl.y).abs();
 
-    let hx = w / 2.;
-    let hy = h / 2.;
-
     let cx = (br.x - tl.x) / 2.;
     let cy = (br.y - tl.y) / 2.;
 
+    commands
+        .spawn(BoxBundle {
+            tl: BoxTopLeft(tl),
+            br: BoxBottomRight(br),
+            collider: Collider::cuboid(w / 2., h / 2.),
+            transform_bundle: TransformBundle::from_transform(Transform::from_xyz(
+                tl.x + cx,
+                tl.y + cy,
+                10.,
+            )),
+            ..default()
+        })
+        .with_children(|cb| {
+            spawn_box_parts(cb, tl, br, one_way, texture_handles, meshes, materials);
+        })
+        .id()
+}
+
+/// Edge colliders and graphics of a box, thrown away and rebuilt when the box changes size
+#[derive(Component)]
+struct BoxPart;
+
+fn spawn_box_parts(
+    cb: &mut ChildBuilder,
+    tl: Vec2,
+    br: Vec2,
+    one_way: bool,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) {
+    let w = (br.x - tl.x).abs();
+    let h = (br.y - tl.y).abs();
+
+    let hx = w / 2.;
+    let hy = h / 2.;
+
     const UV_MAGIC_NUMBER: f32 = 32.;
 
     let mut x_mesh = Mesh::from(shape::Quad::new(Vec2::new(w, 2.5)));
@@ -118,124 +262,267 @@ pub fn create_box(
         }
     }
 
-    commands
-        .spawn(BoxBundle {
-            tl: BoxTopLeft(tl),
-            br: BoxBottomRight(br),
-            collider: Collider::cuboid(w / 2., h / 2.),
-            transform_bundle: TransformBundle::from_transform(Transform::from_xyz(
-                tl.x + cx,
-                tl.y + cy,
-                10.,
-            )),
+    // Edge colliders
+    const EC_WIDTH: f32 = 0.1;
+    const EC_HEIGHT: f32 = 1.0;
+    cb.spawn((
+        Collider::cuboid(EC_WIDTH / 2., EC_HEIGHT / 2.),
+        CollisionGroups::new(Group::GROUP_1, Group::GROUP_1),
+        BoxPart,
+    ))
+    .insert(TransformBundle::from_transform(Transform::from_xyz(
+        -(w + EC_WIDTH) / 2.,
+        (h + EC_HEIGHT) / 2.,
+        10.0,
+    )));
+
+    cb.spawn((
+        Collider::cuboid(EC_WIDTH / 2., EC_HEIGHT / 2.),
+        CollisionGroups::new(Group::GROUP_1, Group::GROUP_1),
+        BoxPart,
+    ))
+    .insert(TransformBundle::from_transform(Transform::from_xyz(
+        (w + EC_WIDTH) / 2.,
+        (h + EC_HEIGHT) / 2.,
+        10.0,
+    )));
+
+    // Graphics
+    // INNER
+    cb.spawn(MaterialMesh2dBundle {
+        mesh: meshes.add(inner_mesh.clone()).into(),
+        material: materials.add(ColorMaterial {
+            // faded so platforms read as something you can pass through
+            color: if one_way {
+                Color::rgba(1., 1., 1., 0.4)
+            } else {
+                Color::WHITE
+            },
+            texture: Some(texture_handles.chalk_box_fill.clone().unwrap()),
+        }),
+        ..default()
+    })
+    .insert(BoxPart);
+    // TOP
+    cb.spawn(MaterialMesh2dBundle {
+        mesh: meshes.add(x_mesh.clone()).into(),
+        transform: Transform::from_xyz(0.0, hy - 1., 0.0),
+        material: materials.add(ColorMaterial {
+            texture: Some(texture_handles.chalk_line_horizontal.clone().unwrap()),
             ..default()
-        })
-        .with_children(|cb| {
-            // Edge colliders
-            const EC_WIDTH: f32 = 0.1;
-            const EC_HEIGHT: f32 = 1.0;
-            cb.spawn((
-                Collider::cuboid(EC_WIDTH / 2., EC_HEIGHT / 2.),
-                CollisionGroups::new(Group::GROUP_1, Group::GROUP_1),
-            ))
-            .insert(TransformBundle::from_transform(Transform::from_xyz(
-                -(w + EC_WIDTH) / 2.,
-                (h + EC_HEIGHT) / 2.,
-                10.0,
-            )));
-
-            cb.spawn((
-                Collider::cuboid(EC_WIDTH / 2., EC_HEIGHT / 2.),
-                CollisionGroups::new(Group::GROUP_1, Group::GROUP_1),
-            ))
-            .insert(TransformBundle::from_transform(Transform::from_xyz(
-                (w + EC_WIDTH) / 2.,
-                (h + EC_HEIGHT) / 2.,
-                10.0,
-            )));
-
-            // Graphics
-            // INNER
-            cb.spawn(MaterialMesh2dBundle {
-                mesh: meshes.add(inner_mesh.clone().into()).into(),
-                material: materials.add(ColorMaterial {
-                    texture: Some(texture_handles.chalk_box_fill.clone().unwrap()),
-                    ..default()
-                }),
-                ..default()
-            });
-            // TOP
-            cb.spawn(MaterialMesh2dBundle {
-                mesh: meshes.add(x_mesh.clone().into()).into(),
-                transform: Transform::from_xyz(0.0, hy - 1., 0.0),
-                material: materials.add(ColorMaterial {
-                    texture: Some(texture_handles.chalk_line_horizontal.clone().unwrap()),
-                    ..default()
-                }),
-                ..default()
-            });
-            // LEFT
-            cb.spawn(MaterialMesh2dBundle {
-                mesh: meshes.add(y_mesh.clone().into()).into(),
-                transform: Transform::from_xyz(-hx + 1., 0.0, 0.0)
-                    .with_rotation(Quat::from_rotation_z(PI / 2.)),
-                material: materials.add(ColorMaterial {
-                    texture: Some(texture_handles.chalk_line_horizontal.clone().unwrap()),
-                    ..default()
-                }),
-                ..default()
-            });
-            // RIGHT
-            cb.spawn(MaterialMesh2dBundle {
-                mesh: meshes.add(y_mesh.clone().into()).into(),
-                transform: Transform::from_xyz(hx - 1., 0.0, 0.0)
-                    .with_rotation(Quat::from_rotation_z(-PI / 2.)),
-                material: materials.add(ColorMaterial {
-                    texture: Some(texture_handles.chalk_line_horizontal.clone().unwrap()),
-                    ..default()
-                }),
-                ..default()
-            });
-            // BOTTOM
-            cb.spawn(MaterialMesh2dBundle {
-                mesh: meshes.add(x_mesh.clone().into()).into(),
-                transform: Transform::from_xyz(0.0, -hy + 1., 0.0)
-                    .with_rotation(Quat::from_rotation_z(PI)),
-                material: materials.add(ColorMaterial {
-                    texture: Some(texture_handles.chalk_line_horizontal.clone().unwrap()),
-                    ..default()
-                }),
-                ..default()
-            });
-        })
-        .id()
+        }),
+        ..default()
+    })
+    .insert(BoxPart);
+
+    // platforms are only solid from the top, so that's the only line drawn
+    if one_way {
+        return;
+    }
+
+    // LEFT
+    cb.spawn(MaterialMesh2dBundle {
+        mesh: meshes.add(y_mesh.clone()).into(),
+        transform: Transform::from_xyz(-hx + 1., 0.0, 0.0)
+            .with_rotation(Quat::from_rotation_z(PI / 2.)),
+        material: materials.add(ColorMaterial {
+            texture: Some(texture_handles.chalk_line_horizontal.clone().unwrap()),
+            ..default()
+        }),
+        ..default()
+    })
+    .insert(BoxPart);
+    // RIGHT
+    cb.spawn(MaterialMesh2dBundle {
+        mesh: meshes.add(y_mesh.clone()).into(),
+        transform: Transform::from_xyz(hx - 1., 0.0, 0.0)
+            .with_rotation(Quat::from_rotation_z(-PI / 2.)),
+        material: materials.add(ColorMaterial {
+            texture: Some(texture_handles.chalk_line_horizontal.clone().unwrap()),
+            ..default()
+        }),
+        ..default()
+    })
+    .insert(BoxPart);
+    // BOTTOM
+    cb.spawn(MaterialMesh2dBundle {
+        mesh: meshes.add(x_mesh.clone()).into(),
+        transform: Transform::from_xyz(0.0, -hy + 1., 0.0).with_rotation(Quat::from_rotation_z(PI)),
+        material: materials.add(ColorMaterial {
+            texture: Some(texture_handles.chalk_line_horizontal.clone().unwrap()),
+            ..default()
+        }),
+        ..default()
+    })
+    .insert(BoxPart);
+}
+
+// boxes changed after spawning (by the editor) need new parts to match their size
+#[allow(clippy::type_complexity)]
+fn rebuild_box_parts(
+    mut commands: Commands,
+    q_boxes: Query<
+        (
+            Entity,
+            &BoxTopLeft,
+            &BoxBottomRight,
+            ChangeTrackers<BoxTopLeft>,
+            Option<&Children>,
+            Option<&OneWayPlatform>,
+        ),
+        (
+            With<Wall>,
+            Or<(Changed<BoxTopLeft>, Changed<BoxBottomRight>)>,
+        ),
+    >,
+    q_parts: Query<Entity, With<BoxPart>>,
+    texture_handles: Res<TextureHandles>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, tl, br, tl_tracker, children, platform) in &q_boxes {
+        // create_box already built these
+        if tl_tracker.is_added() {
+            continue;
+        }
+
+        for &child in children.into_iter().flatten() {
+            if q_parts.contains(child) {
+                commands.entity(child).despawn_recursive();
+            }
+        }
+
+        commands.entity(entity).with_children(|cb| {
+            spawn_box_parts(
+                cb,
+                tl.0,
+                br.0,
+                platform.is_some(),
+                &texture_handles,
+                &mut meshes,
+                &mut materials,
+            );
+        });
+    }
 }
 
 #[derive(Component)]
 struct RespectBarFill;
 
-const BAR_HEIGHT: f32 = 66.;
-const BAR_WIDTH: f32 = BAR_HEIGHT * 4.;
+/// One of the player's hits, `.0` from the left
+#[derive(Component)]
+struct HealthIcon(u32);
+
+#[derive(Component)]
+struct LivesText;
+
+/// Red over the whole screen when the player gets hurt
+#[derive(Component)]
+struct ScreenFlash;
+
+const HEALTH_ICON_SIZE: f32 = 40.;
+const HEALTH_FULL_COLOR: Color = Color::RED;
+const HEALTH_LOST_COLOR: Color = Color::rgba(1., 1., 1., 0.3);
+
+// how long the flash takes to fade, and how strong it starts
+const FLASH_TIME: f32 = 0.3;
+const FLASH_ALPHA: f32 = 0.4;
+
+pub const BAR_HEIGHT: f32 = 66.;
+pub const BAR_WIDTH: f32 = BAR_HEIGHT * 4.;
 const FILL_MARGIN: f32 = 0.05;
-const FILL_WIDTH: f32 = BAR_WIDTH * (1. - FILL_MARGIN * 2.);
-const TOTAL_ENEMIES: f32 = 14.;
+pub const FILL_WIDTH: f32 = BAR_WIDTH * (1. - FILL_MARGIN * 2.);
 
-fn setup_ingame_ui(mut commands: Commands, texture_handles: Res<TextureHandles>) {
-    // respect bar
+fn setup_ingame_ui(
+    mut commands: Commands,
+    texture_handles: Res<TextureHandles>,
+    ui_font: Res<UiFont>,
+) {
+    // under everything else
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(ScreenFlash)
+        .insert(InGameItem);
+
+    // hearts and lives, just left of the respect meter
     commands
         .spawn(NodeBundle {
             style: Style {
                 position_type: PositionType::Absolute,
                 position: UiRect {
-                    right: Val::Px(0.),
+                    right: Val::Px(BAR_WIDTH + 10.),
                     ..default()
                 },
-                size: Size::new(Val::Px(BAR_WIDTH), Val::Px(BAR_HEIGHT)),
+                size: Size::new(Val::Auto, Val::Px(BAR_HEIGHT)),
+                align_items: AlignItems::Center,
                 ..default()
             },
             ..default()
         })
         .insert(InGameItem)
+        .with_children(|cb| {
+            for i in 0..PLAYER_MAX_HEALTH {
+                cb.spawn(ImageBundle {
+                    image: UiImage(texture_handles.char_body.clone().unwrap()),
+                    style: Style {
+                        size: Size::new(Val::Px(HEALTH_ICON_SIZE), Val::Px(HEALTH_ICON_SIZE)),
+                        ..default()
+                    },
+                    background_color: HEALTH_FULL_COLOR.into(),
+                    ..default()
+                })
+                .insert(HealthIcon(i));
+            }
+
+            cb.spawn(TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: ui_font.0.clone(),
+                    font_size: 40.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            ))
+            .insert(LivesText);
+        });
+
+    let respect_bar = spawn_meter(
+        &mut commands,
+        &texture_handles,
+        UiRect {
+            right: Val::Px(0.),
+            ..default()
+        },
+        RespectBarFill,
+    );
+    commands.entity(respect_bar).insert(InGameItem);
+}
+
+/// A bar like the respect meter at `position` on screen. Its fill is clipped by
+/// a node tagged with `fill`, whose width is `FILL_WIDTH` when full
+pub fn spawn_meter(
+    commands: &mut Commands,
+    texture_handles: &TextureHandles,
+    position: UiRect,
+    fill: impl Component,
+) -> Entity {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position,
+                size: Size::new(Val::Px(BAR_WIDTH), Val::Px(BAR_HEIGHT)),
+                ..default()
+            },
+            ..default()
+        })
         .with_children(|cb| {
             // bar outline
             cb.spawn(ImageBundle {
@@ -263,7 +550,7 @@ fn setup_ingame_ui(mut commands: Commands, texture_handles: Res<TextureHandles>)
                 },
                 ..default()
             })
-            .insert(RespectBarFill)
+            .insert(fill)
             .with_children(|cb| {
                 // fill
                 cb.spawn(ImageBundle {
@@ -276,320 +563,270 @@ fn setup_ingame_ui(mut commands: Commands, texture_handles: Res<TextureHandles>)
                     ..default()
                 });
             });
-        });
+        })
+        .id()
 }
 
 fn update_respect_meter(
     mut q_respect_style: Query<&mut Style, With<RespectBarFill>>,
     q_enemies: Query<&Enemy>,
+    karma: Res<Karma>,
+    level_enemy_count: Option<Res<LevelEnemyCount>>,
 ) {
-    let pc: f32 = 1. - q_enemies.iter().count() as f32 / (TOTAL_ENEMIES);
+    let Some(level_enemy_count) = level_enemy_count else {
+        return;
+    };
+
+    // the whole run so far, not just this level
+    let run_killed = karma.count(KarmaEvent::Kill);
+    let run_total = run_killed + karma.count(KarmaEvent::Spare);
+    let killed = run_killed + level_enemy_count.0.saturating_sub(q_enemies.iter().count());
+    let total = run_total + level_enemy_count.0;
+
+    let pc: f32 = if total == 0 {
+        0.
+    } else {
+        killed as f32 / total as f32
+    };
     q_respect_style.single_mut().size.width = Val::Px(FILL_WIDTH * pc);
 }
 
+fn update_player_hud(
+    q_player: Query<&PlayerHealth>,
+    mut q_icons: Query<(&HealthIcon, &mut BackgroundColor)>,
+    mut q_lives_text: Query<&mut Text, With<LivesText>>,
+    mut q_flash: Query<&mut BackgroundColor, (With<ScreenFlash>, Without<HealthIcon>)>,
+    lives: Res<Lives>,
+) {
+    // nothing to show between dying and respawning
+    let Ok(health) = q_player.get_single() else {
+        return;
+    };
+
+    for (HealthIcon(i), mut color) in &mut q_icons {
+        let wanted = if *i < health.health {
+            HEALTH_FULL_COLOR
+        } else {
+            HEALTH_LOST_COLOR
+        };
+        if color.0 != wanted {
+            color.0 = wanted;
+        }
+    }
+
+    for mut text in &mut q_lives_text {
+        let wanted = format!("x{}", lives.0);
+        if text.sections[0].value != wanted {
+            text.sections[0].value = wanted;
+        }
+    }
+
+    // fades out over the start of the invulnerability after a hit
+    let since_hit = PLAYER_INVULNERABLE_TIME - health.invulnerable;
+    let flash = if health.invulnerable > 0. && since_hit < FLASH_TIME {
+        FLASH_ALPHA * (1. - since_hit / FLASH_TIME)
+    } else {
+        0.
+    };
+    for mut color in &mut q_flash {
+        color.0 = Color::rgba(1., 0., 0., flash);
+    }
+}
+
 // todo: enum
 pub const FLOOR_0: f32 = -10.;
 pub const FLOOR_1: f32 = 0.;
 pub const FLOOR_0_BOTTOM: f32 = -15.;
-pub const FLOOR_1_BOTTOM: f32 = -5.;
 
 #[derive(Resource)]
 pub struct LevelEnemyCount(pub usize);
 
+/// Entered the game before the level finished loading, `setup_level` keeps
+/// trying until it has
+#[derive(Resource)]
+struct LevelPending;
+
+#[allow(clippy::too_many_arguments)]
 fn setup_level(
     mut commands: Commands,
     texture_handles: Res<TextureHandles>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    level_handles: Res<LevelHandles>,
+    levels: Res<Assets<LevelData>>,
+    current_level: Res<CurrentLevel>,
+    playtest: Option<Res<Playtest>>,
+    checkpoint: Option<Res<ReachedCheckpoint>>,
 ) {
-    // const MAX_JUMP: f32 = 12.;
-
-    spawn_player(
-        &mut commands,
-        &texture_handles,
-        // Vec3::new(174., FLOOR_0 + 14.8, 10.), // end testing
-        Vec3::new(0., FLOOR_0 + 0.8, 10.), // start
-        // Vec3::new(115., FLOOR_0 + 3., 10.), // overhang testing
-    );
-
-    // first static enemy
-    spawn_enemy(
-        &mut commands,
-        &texture_handles,
-        Vec3::new(10.0, FLOOR_0 + 0.8, 10.0),
-        false,
-    );
-
-    create_box(
-        &mut commands,
-        Vec2::new(-20., 100.),
-        Vec2::new(-10., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
-
-    create_box(
-        &mut commands,
-        Vec2::new(-10., FLOOR_0),
-        Vec2::new(15., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
-
-    // first moving enemy
-    spawn_enemy(
-        &mut commands,
-        &texture_handles,
-        Vec3::new(25.0, FLOOR_0 + 0.8, 10.0),
-        true,
-    );
-
-    create_box(
-        &mut commands,
-        Vec2::new(20., FLOOR_0),
-        Vec2::new(30., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
-
-    // higher level
-
-    create_box(
-        &mut commands,
-        Vec2::new(30., FLOOR_1),
-        Vec2::new(50., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
-
-    // big jump down from higher up
-
-    create_box(
-        &mut commands,
-        Vec2::new(62., FLOOR_0),
-        Vec2::new(75., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
-
-    // jump to higher level with gap
-    spawn_enemy(
-        &mut commands,
-        &texture_handles,
-        Vec3::new(82.5, FLOOR_1 + 1., 10.),
-        false,
-    );
-    create_box(
-        &mut commands,
-        Vec2::new(80., FLOOR_1),
-        Vec2::new(85., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
-
-    // jump down past gap onto small platform with moving enemy
+    // playtests always start from the top
+    let checkpoint = match playtest {
+        Some(_) => None,
+        None => checkpoint.as_deref(),
+    };
+
+    // playtesting from the editor replaces the built in levels
+    let (level, trigger) = match &playtest {
+        Some(playtest) => (Some(&playtest.level), Trigger::Ending),
+        None => {
+            let trigger = if current_level.0 + 1 < LEVEL_PATHS.len() {
+                Trigger::NextLevel
+            } else {
+                Trigger::Ending
+            };
+
+            let level = level_handles
+                .0
+                .get(current_level.0)
+                .and_then(|handle| levels.get(handle));
+
+            (level, trigger)
+        }
+    };
+
+    if let Some(level) = level {
+        commands.remove_resource::<LevelPending>();
+        spawn_level(
+            &mut commands,
+            level,
+            trigger,
+            checkpoint,
+            &texture_handles,
+            &mut meshes,
+            &mut materials,
+        );
+    } else {
+        commands.insert_resource(LevelPending);
+    }
+}
 
-    spawn_enemy(
-        &mut commands,
-        &texture_handles,
-        Vec3::new(96., FLOOR_0 + 1., 10.),
-        true,
-    );
-    create_box(
-        &mut commands,
-        Vec2::new(95., FLOOR_0),
-        Vec2::new(98., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
+/// Spawn everything described by a `LevelData`, picking up from `checkpoint` if
+/// there is one
+pub fn spawn_level(
+    commands: &mut Commands,
+    level: &LevelData,
+    trigger: Trigger,
+    checkpoint: Option<&ReachedCheckpoint>,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) {
+    let player_spawn = checkpoint.map_or(level.player_spawn, |c| c.position);
+    spawn_player(commands, texture_handles, player_spawn.extend(10.));
+
+    for (index, enemy) in level.enemies.iter().enumerate() {
+        // anything killed before the checkpoint stays dead
+        if let Some(checkpoint) = checkpoint {
+            if !checkpoint.alive_enemies.contains(&index) {
+                continue;
+            }
+        }
 
-    // little gap little platform
+        let e = spawn_enemy(commands, texture_handles, *enemy);
+        commands.entity(e).insert(LevelEnemy(index));
+    }
 
-    create_box(
-        &mut commands,
-        Vec2::new(102., FLOOR_0),
-        Vec2::new(105., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
+    // he's back at full health after a death, so whatever happened to him before doesn't count
+    if let Some(position) = level.boss {
+        spawn_boss(commands, texture_handles, position);
+        commands.insert_resource(BossResult::default());
+    }
 
-    // overhang
-    spawn_enemy(
-        &mut commands,
-        &texture_handles,
-        Vec3::new(115., FLOOR_0 + 1., 10.),
-        true,
-    );
+    for (index, &position) in level.checkpoints.iter().enumerate() {
+        let e = spawn_checkpoint(commands, index, position);
 
-    create_box(
-        &mut commands,
-        Vec2::new(110., FLOOR_0),
-        Vec2::new(120., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
+        if checkpoint.map(|c| c.index) == Some(index) {
+            commands.entity(e).insert(Sprite {
+                color: CHECKPOINT_REACHED_COLOR,
+                custom_size: Some(CHECKPOINT_SIZE),
+                ..default()
+            });
+        }
+    }
 
-    create_box(
-        &mut commands,
-        Vec2::new(115., FLOOR_1),
-        Vec2::new(120., FLOOR_1_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
+    for b in &level.boxes {
+        create_box(commands, b.tl, b.br, texture_handles, meshes, materials);
+    }
 
-    create_box(
-        &mut commands,
-        Vec2::new(120., FLOOR_1),
-        Vec2::new(130., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
+    for p in &level.platforms {
+        create_platform(commands, p.tl, p.br, texture_handles, meshes, materials);
+    }
 
-    // longish platform
-    spawn_enemy(
-        &mut commands,
-        &texture_handles,
-        Vec3::new(141., FLOOR_0 + 1., 10.),
-        true,
-    );
+    for p in &level.moving_platforms {
+        create_moving_platform(commands, p, texture_handles, meshes, materials);
+    }
 
-    spawn_enemy(
-        &mut commands,
-        &texture_handles,
-        Vec3::new(144., FLOOR_0 + 1., 10.),
-        true,
-    );
+    for &p in &level.crumbling_platforms {
+        create_crumbling_platform(commands, p, texture_handles, meshes, materials);
+    }
 
-    spawn_enemy(
-        &mut commands,
-        &texture_handles,
-        Vec3::new(147., FLOOR_0 + 1., 10.),
-        true,
-    );
+    for &slope in &level.slopes {
+        spawn_slope(commands, slope, texture_handles, meshes, materials);
+    }
 
-    spawn_enemy(
-        &mut commands,
-        &texture_handles,
-        Vec3::new(159., FLOOR_0 + 1., 10.),
-        false,
-    );
+    for &hazard in &level.hazards {
+        spawn_hazard(commands, hazard, texture_handles, meshes, materials);
+    }
 
-    create_box(
-        &mut commands,
-        Vec2::new(140., FLOOR_0),
-        Vec2::new(160., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
+    commands.insert_resource(DeathPlane(level.death_plane));
 
-    // steps
-    spawn_enemy(
-        &mut commands,
-        &texture_handles,
-        Vec3::new(162., FLOOR_0 + 4., 10.),
-        false,
-    );
-    create_box(
-        &mut commands,
-        Vec2::new(160., FLOOR_0 + 3.),
-        Vec2::new(164., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
+    spawn_trigger(commands, level.trigger, trigger);
 
-    spawn_enemy(
-        &mut commands,
-        &texture_handles,
-        Vec3::new(166., FLOOR_0 + 7., 10.),
-        false,
-    );
-    create_box(
-        &mut commands,
-        Vec2::new(164., FLOOR_0 + 6.),
-        Vec2::new(168., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
+    // enter level enemy count
+    commands.insert_resource(LevelEnemyCount(level.enemy_count));
+}
 
-    spawn_enemy(
-        &mut commands,
-        &texture_handles,
-        Vec3::new(170., FLOOR_0 + 10., 10.),
-        false,
-    );
-    create_box(
-        &mut commands,
-        Vec2::new(168., FLOOR_0 + 9.),
-        Vec2::new(172., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
+/// The end of a level, touching it does whatever `kind` says
+pub fn spawn_trigger(commands: &mut Commands, trigger: BoxData, kind: Trigger) -> Entity {
+    let half_extents = ((trigger.br - trigger.tl) / 2.).abs();
+    let center = (trigger.tl + trigger.br) / 2.;
 
-    spawn_enemy(
-        &mut commands,
-        &texture_handles,
-        Vec3::new(174., FLOOR_0 + 13., 10.),
-        false,
-    );
-    create_box(
-        &mut commands,
-        Vec2::new(172., FLOOR_0 + 12.),
-        Vec2::new(176., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
+    commands
+        .spawn(Collider::cuboid(half_extents.x, half_extents.y))
+        .insert(Sensor)
+        .insert(kind)
+        .insert(InGameItem)
+        .insert(BoxTopLeft(trigger.tl))
+        .insert(BoxBottomRight(trigger.br))
+        .insert(TransformBundle::from_transform(
+            Transform::from_translation(center.extend(10.)),
+        ))
+        .id()
+}
 
-    // big gap, finish platform
-    create_box(
-        &mut commands,
-        Vec2::new(188., FLOOR_0),
-        Vec2::new(300., FLOOR_0_BOTTOM),
-        &texture_handles,
-        &mut meshes,
-        &mut materials,
-    );
+pub const CHECKPOINT_SIZE: Vec2 = Vec2::new(0.3, 3.);
+pub const CHECKPOINT_COLOR: Color = Color::rgb(0.5, 0.5, 0.5);
+pub const CHECKPOINT_REACHED_COLOR: Color = Color::rgb(0.2, 0.8, 0.2);
 
-    // ending cutscene trigger
+// a post the player respawns next to
+pub fn spawn_checkpoint(commands: &mut Commands, index: usize, position: Vec2) -> Entity {
     commands
-        .spawn(Collider::cuboid(16., 1.))
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: CHECKPOINT_COLOR,
+                custom_size: Some(CHECKPOINT_SIZE),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(9.)),
+            ..default()
+        })
+        // wider than the post, so running past counts
+        .insert(Collider::cuboid(1., CHECKPOINT_SIZE.y / 2.))
         .insert(Sensor)
-        .insert(Trigger)
+        .insert(Checkpoint(index))
         .insert(InGameItem)
-        .insert(TransformBundle::from_transform(Transform::from_xyz(
-            204.,
-            FLOOR_0 - 0.5,
-            10.,
-        )));
-
-    // enter level enemy count
-    commands.insert_resource(LevelEnemyCount(13));
+        .id()
 }
 
 fn actor_fall_out(
     mut commands: Commands,
     query: Query<(&Transform, Entity), With<Actor>>,
+    death_plane: Res<DeathPlane>,
     audio: Res<Audio>,
     sound_collection: Res<SoundCollection>,
     audio_volume: Res<AudioVolume>,
 ) {
     for (transfrorm, entity) in &query {
-        if transfrorm.translation.y < DEATHPLANE {
+        if transfrorm.translation.y < death_plane.0 {
             audio.play_with_settings(sound_collection.fall.clone(), PlaybackSettings::ONCE.with_volume(audio_volume.0));
             commands.entity(entity).insert(ActorDead);
         }
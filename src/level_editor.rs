@@ -1,11 +1,40 @@
 use bevy::prelude::*;
-use bevy_rapier2d::{na::Vector2, prelude::Collider};
+use bevy_rapier2d::{
+    na::Vector2,
+    prelude::{Collider, KinematicCharacterController, QueryFilter, RapierContext, Sensor},
+};
 use iyes_loopless::{
     prelude::{AppLooplessStateExt, ConditionHelpers, IntoConditionalSystem},
     state::NextState,
 };
 
-use crate::{level::create_box, states::GameState, util::despawn_with, TextureHandles};
+use crate::{
+    boss::{spawn_boss, Boss, BOSS_RADIUS},
+    editor_history::{EditorCommand, EditorHistory, EditorId, EditorItem},
+    enemy::{spawn_enemy, Enemy, EnemyHealth, EnemyMover, BEHAVIOURS},
+    hazards::{spawn_hazard, Hazard},
+    interfaces::{AudioVolume, MenuButton, QuitButton},
+    kinematic_physics::PhysicsInterpolation,
+    level::{
+        box_corners, create_box, create_platform, spawn_checkpoint, spawn_trigger, BoxBottomRight,
+        BoxTopLeft, Checkpoint, DeathPlane, OneWayPlatform, Trigger, Wall,
+    },
+    level_loader::{
+        read_level_file, write_level_file, BoxData, EnemyBehaviour, EnemyData, HazardData,
+        HazardKind, LevelData, MovingPlatformData, SlopeData, SlopeShape,
+    },
+    platforms::{
+        create_crumbling_platform, create_moving_platform, CrumblingPlatform, MovingPlatform,
+    },
+    player::PLAYER_RADIUS,
+    slopes::{slope_fill_collider, spawn_slope, Slope},
+    states::{GameState, PauseState},
+    util::despawn_with,
+    SoundCollection, TextureHandles, UiFont,
+};
+
+pub const EDITOR_LEVEL_PATH: &str = "assets/levels/editor.level.ron";
+
 pub struct LevelEditorPlugin;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -16,6 +45,12 @@ pub enum EditorTool {
     Select, // And create
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemLabel)]
+enum EditorLabel {
+    Cursor,
+    MousePress,
+}
+
 #[derive(Component)]
 struct LevelEditorItem;
 
@@ -28,28 +63,234 @@ struct Crosshair;
 #[derive(Component)]
 struct ToolText;
 
+#[derive(Component)]
+pub struct PlayerSpawn;
+
+#[derive(Component)]
+struct EditorMenuItem;
+
+#[derive(Component)]
+struct SaveButton;
+
+#[derive(Component)]
+struct LoadButton;
+
+#[derive(Component)]
+struct GridText;
+
+#[derive(Component)]
+struct DragPreview;
+
+#[derive(Component)]
+struct SelectionHighlight;
+
+#[derive(Component)]
+struct TriggerSprite;
+
+/// Sensor filling a slope's real shape, for clicks to land on
+#[derive(Component)]
+struct SlopeClickArea;
+
+#[derive(Component)]
+struct WaypointMarker;
+
+#[derive(Component)]
+struct PatrolMarker;
+
+#[derive(Component)]
+struct HealthMarker;
+
+/// The editor layout while it's being playtested. Only exists between leaving
+/// the editor to playtest and coming back to it
+#[derive(Resource)]
+pub struct Playtest {
+    pub level: LevelData,
+    items: Vec<(EditorId, EditorItem)>,
+    camera: Vec3,
+}
+
+/// World space grid the editor cursor snaps to
+#[derive(Resource)]
+pub struct SnapGrid {
+    pub size: f32,
+    pub enabled: bool,
+}
+
+impl Default for SnapGrid {
+    fn default() -> Self {
+        Self {
+            size: 1.,
+            enabled: true,
+        }
+    }
+}
+
+impl SnapGrid {
+    pub fn snap(&self, point: Vec2) -> Vec2 {
+        if self.enabled {
+            (point / self.size).round() * self.size
+        } else {
+            point
+        }
+    }
+}
+
+/// Mouse position in world space, raw and snapped to the grid
+#[derive(Resource, Default)]
+struct EditorCursor {
+    world: Vec2,
+    snapped: Vec2,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct GrabbedEdges {
+    left: bool,
+    right: bool,
+    top: bool,
+    bottom: bool,
+}
+
+#[derive(Resource, Default)]
+enum EditorDrag {
+    #[default]
+    None,
+    Create {
+        start: Vec2,
+    },
+    Resize {
+        entity: Entity,
+        edges: GrabbedEdges,
+        before: BoxData,
+    },
+}
+
 impl Plugin for LevelEditorPlugin {
     fn build(&self, app: &mut App) {
         app.add_loopless_state(EditorTool::Select)
+            .init_resource::<SnapGrid>()
+            .init_resource::<EditorCursor>()
+            .init_resource::<EditorDrag>()
+            .init_resource::<EditorHistory>()
             .add_enter_system(GameState::LevelEditor, setup_level_editor)
             .add_exit_system(GameState::LevelEditor, cleanup_level_editor)
             // input
             .add_system(
                 editor_tool_expand
                     .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running)
                     .run_in_state(EditorTool::Expand),
             )
             .add_system(
-                move_crosshair
+                editor_tool_move
                     .run_in_state(GameState::LevelEditor)
-                    .run_in_state(EditorTool::Select),
+                    .run_in_state(PauseState::Running)
+                    .run_in_state(EditorTool::Move),
+            )
+            .add_system(
+                editor_tool_shrink
+                    .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running)
+                    .run_in_state(EditorTool::Shrink),
+            )
+            .add_system(
+                update_editor_cursor
+                    .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running)
+                    .label(EditorLabel::Cursor),
+            )
+            .add_system(
+                pan_camera
+                    .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running)
+                    .run_in_state(EditorTool::Select)
+                    .before(EditorLabel::Cursor),
             )
             .add_system(
                 editor_create_box
                     .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running)
+                    .run_in_state(EditorTool::Select)
+                    .after(EditorLabel::Cursor),
+            )
+            .add_system(
+                editor_place_items
+                    .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running)
+                    .run_in_state(EditorTool::Select)
+                    .after(EditorLabel::Cursor),
+            )
+            .add_system(
+                editor_add_waypoint
+                    .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running)
+                    .run_in_state(EditorTool::Select)
+                    .after(EditorLabel::Cursor),
+            )
+            .add_system(
+                editor_edit_patrol
+                    .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running)
+                    .run_in_state(EditorTool::Select)
+                    .after(EditorLabel::Cursor),
+            )
+            .add_system(
+                editor_edit_enemy
+                    .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running)
                     .run_in_state(EditorTool::Select),
             )
-            .add_system(level_editor_input.run_in_state(GameState::LevelEditor))
+            .add_system(
+                editor_mouse_press
+                    .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running)
+                    .run_in_state(EditorTool::Select)
+                    .after(EditorLabel::Cursor)
+                    .label(EditorLabel::MousePress),
+            )
+            .add_system(
+                editor_mouse_drag
+                    .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running)
+                    .run_in_state(EditorTool::Select)
+                    .after(EditorLabel::MousePress),
+            )
+            .add_system(
+                snap_grid_input
+                    .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running),
+            )
+            .add_system(update_grid_text.run_in_state(GameState::LevelEditor))
+            .add_system(resize_trigger_sprite.run_in_state(GameState::LevelEditor))
+            .add_system(resize_slope_click_area.run_in_state(GameState::LevelEditor))
+            .add_system(show_selected.run_in_state(GameState::LevelEditor))
+            .add_system(
+                level_editor_input
+                    .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running),
+            )
+            .add_system(
+                editor_delete_selected
+                    .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running),
+            )
+            .add_system(
+                editor_undo_redo
+                    .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running),
+            )
+            .add_system(
+                editor_playtest
+                    .run_in_state(GameState::LevelEditor)
+                    .run_in_state(PauseState::Running),
+            )
+            // editor menu
+            .add_enter_system(
+                PauseState::Paused,
+                setup_editor_menu.run_in_state(GameState::LevelEditor),
+            )
+            .add_exit_system(PauseState::Paused, despawn_with::<EditorMenuItem>)
+            .add_system(save_button.run_in_state(GameState::LevelEditor))
+            .add_system(load_button.run_in_state(GameState::LevelEditor))
             // ui
             .add_enter_system(
                 EditorTool::Select,
@@ -77,15 +318,60 @@ fn ui_shrink_enter(mut q_tool_text: Query<&mut Text, With<ToolText>>) {
     q_tool_text.single_mut().sections[0].value = "SHRINK".into();
 }
 
+#[allow(clippy::too_many_arguments)]
 fn setup_level_editor(
     mut commands: Commands,
     texture_handles: Res<TextureHandles>,
     mut q_camera: Query<&mut Transform, (With<Camera2d>, Without<Crosshair>)>,
     asset_server: Res<AssetServer>,
+    mut history: ResMut<EditorHistory>,
+    playtest: Option<Res<Playtest>>,
+
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
     let mut t_cam = q_camera.single_mut();
 
-    t_cam.translation = Vec3::new(0., 0., 0.);
+    commands.insert_resource(EditorDrag::None);
+
+    if let Some(playtest) = &playtest {
+        // back from a playtest, put everything back where it was. Ids are
+        // kept so the history still applies
+        t_cam.translation = playtest.camera;
+
+        for (id, item) in &playtest.items {
+            spawn_editor_item(
+                &mut commands,
+                *id,
+                item.clone(),
+                &texture_handles,
+                &mut meshes,
+                &mut materials,
+            );
+        }
+
+        commands.remove_resource::<Playtest>();
+    } else {
+        t_cam.translation = Vec3::new(0., 0., 0.);
+        *history = EditorHistory::default();
+        commands.insert_resource(DeathPlane::default());
+
+        // every level needs somewhere to start and somewhere to finish
+        spawn_editor_player_spawn(
+            &mut commands,
+            history.new_id(),
+            &texture_handles,
+            Vec2::new(0., 0.),
+        );
+        spawn_editor_trigger(
+            &mut commands,
+            history.new_id(),
+            BoxData {
+                tl: Vec2::new(10., 1.),
+                br: Vec2::new(14., -1.),
+            },
+        );
+    }
 
     commands
         .spawn(SpriteBundle {
@@ -100,6 +386,18 @@ fn setup_level_editor(
         .insert(Crosshair)
         .insert(LevelEditorItem);
 
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.2, 0.6, 1., 0.4),
+                ..default()
+            },
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(SelectionHighlight)
+        .insert(LevelEditorItem);
+
     // ui
     commands
         .spawn(TextBundle {
@@ -116,131 +414,2167 @@ fn setup_level_editor(
         })
         .insert(ToolText)
         .insert(LevelEditorItem);
+
+    commands
+        .spawn(TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 30.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(0.),
+                    top: Val::Px(45.),
+                    ..default()
+                },
+                ..default()
+            },
+            ..default()
+        })
+        .insert(GridText)
+        .insert(LevelEditorItem);
 }
 
-fn deselect(commands: &mut Commands, q_currently_selected: &Query<Entity, With<EditorSelected>>) {
-    for entity in q_currently_selected {
-        commands.entity(entity).remove::<EditorSelected>();
-    }
+fn spawn_editor_box(
+    commands: &mut Commands,
+    id: EditorId,
+    tl: Vec2,
+    br: Vec2,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) -> Entity {
+    let e = create_box(commands, tl, br, texture_handles, meshes, materials);
+    commands.entity(e).insert(LevelEditorItem).insert(id);
+    e
 }
 
-fn move_crosshair(
-    mut q_camera: Query<&mut Transform, (With<Camera2d>, Without<Crosshair>)>,
-    mut q_crosshair: Query<&mut Transform, (With<Crosshair>, Without<Camera2d>)>,
-    input: Res<Input<KeyCode>>,
-) {
-    let mut t_cam = q_camera.single_mut();
-    let mut t_cross = q_crosshair.single_mut();
+fn spawn_editor_platform(
+    commands: &mut Commands,
+    id: EditorId,
+    tl: Vec2,
+    br: Vec2,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) -> Entity {
+    let e = create_platform(commands, tl, br, texture_handles, meshes, materials);
+    commands.entity(e).insert(LevelEditorItem).insert(id);
+    e
+}
 
-    if input.just_pressed(KeyCode::D) {
-        t_cam.translation.x += 1.;
-        t_cross.translation.x += 1.;
-    }
+fn spawn_editor_moving_platform(
+    commands: &mut Commands,
+    id: EditorId,
+    platform: &MovingPlatformData,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) -> Entity {
+    let e = create_moving_platform(commands, platform, texture_handles, meshes, materials);
+    // platforms don't move in the editor, and interpolation would undo the move tool
+    commands
+        .entity(e)
+        .remove::<PhysicsInterpolation>()
+        .insert(LevelEditorItem)
+        .insert(id)
+        .with_children(|cb| {
+            // waypoints are relative to the platform, so markers follow it around
+            for waypoint in &platform.waypoints {
+                cb.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(0., 1., 1., 0.6),
+                        custom_size: Some(Vec2::splat(WAYPOINT_MARKER_SIZE)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(waypoint.extend(1.)),
+                    ..default()
+                })
+                .insert(WaypointMarker);
+            }
+        });
+    e
+}
 
-    if input.just_pressed(KeyCode::A) {
-        t_cam.translation.x += -1.;
-        t_cross.translation.x += -1.;
-    }
+const WAYPOINT_MARKER_SIZE: f32 = 0.5;
 
-    if input.just_pressed(KeyCode::W) {
-        t_cross.translation.y += 1.;
-    }
+fn spawn_editor_crumbling_platform(
+    commands: &mut Commands,
+    id: EditorId,
+    platform: BoxData,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) -> Entity {
+    let e = create_crumbling_platform(commands, platform, texture_handles, meshes, materials);
+    commands.entity(e).insert(LevelEditorItem).insert(id);
+    e
+}
 
-    if input.just_pressed(KeyCode::S) {
-        t_cross.translation.y += -1.;
-    }
+fn spawn_editor_hazard(
+    commands: &mut Commands,
+    id: EditorId,
+    hazard: HazardData,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) -> Entity {
+    let e = spawn_hazard(commands, hazard, texture_handles, meshes, materials);
+    commands.entity(e).insert(LevelEditorItem).insert(id);
+    e
 }
 
-fn editor_create_box(
-    mut commands: Commands,
-    input: Res<Input<KeyCode>>,
-    q_crosshair: Query<&mut Transform, (With<Crosshair>, Without<Camera2d>)>,
+fn spawn_editor_slope(
+    commands: &mut Commands,
+    id: EditorId,
+    slope: SlopeData,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) -> Entity {
+    let e = spawn_slope(commands, slope, texture_handles, meshes, materials);
+    let half_extents = ((slope.area.br - slope.area.tl) / 2.).abs();
+    commands
+        .entity(e)
+        .insert(LevelEditorItem)
+        .insert(id)
+        // only here for the box tools, which size things by it. Clicks skip
+        // it for the click area, so the empty corners of the box don't count
+        .insert(Collider::cuboid(half_extents.x, half_extents.y))
+        .insert(Sensor)
+        .with_children(|cb| {
+            cb.spawn((slope_fill_collider(slope), Sensor, SlopeClickArea))
+                .insert(TransformBundle::default());
+        });
+    e
+}
 
-    q_currently_selected: Query<Entity, With<EditorSelected>>,
+fn spawn_editor_enemy(
+    commands: &mut Commands,
+    id: EditorId,
+    texture_handles: &TextureHandles,
+    enemy: EnemyData,
+) -> Entity {
+    let e = spawn_enemy(commands, texture_handles, enemy);
+    // no controller, no movement. editor enemies stay where they're put
+    commands
+        .entity(e)
+        .remove::<KinematicCharacterController>()
+        .remove::<PhysicsInterpolation>()
+        .insert(LevelEditorItem)
+        .insert(id)
+        .with_children(|cb| {
+            // posts where it turns around, and feelers if it looks for ledges
+            let mut markers = vec![];
+            if let Some(left) = enemy.patrol_left {
+                markers.push((Vec2::new(-left, 0.), PATROL_BOUND_SIZE));
+            }
+            if let Some(right) = enemy.patrol_right {
+                markers.push((Vec2::new(right, 0.), PATROL_BOUND_SIZE));
+            }
+            if enemy.turn_at_ledges {
+                for x in [-PLAYER_RADIUS, PLAYER_RADIUS] {
+                    markers.push((Vec2::new(x, -PLAYER_RADIUS), LEDGE_FEELER_SIZE));
+                }
+            }
 
-    texture_handles: Res<TextureHandles>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-) {
-    let t_cross = q_crosshair.single();
+            for (offset, size) in markers {
+                cb.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(1., 0.5, 0., 0.6),
+                        custom_size: Some(size),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(offset.extend(1.)),
+                    ..default()
+                })
+                .insert(PatrolMarker);
+            }
 
-    if input.just_pressed(KeyCode::B) {
-        let new_box = create_box(
-            &mut commands,
-            Vec2::new(t_cross.translation.x, t_cross.translation.y),
-            Vec2::new(t_cross.translation.x + 1.0, t_cross.translation.y - 1.0),
-            &texture_handles,
-            &mut meshes,
-            &mut materials,
-        );
+            // a pip above its head for every stomp it takes
+            if enemy.health > 1 {
+                let width = (enemy.health - 1) as f32 * HEALTH_PIP_SPACING;
+                for i in 0..enemy.health {
+                    let x = i as f32 * HEALTH_PIP_SPACING - width / 2.;
+                    cb.spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::WHITE,
+                            custom_size: Some(HEALTH_PIP_SIZE),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(x, PLAYER_RADIUS + 0.4, 1.),
+                        ..default()
+                    })
+                    .insert(HealthMarker);
+                }
+            }
+        });
+    e
+}
 
-        deselect(&mut commands, &q_currently_selected);
+const HEALTH_PIP_SIZE: Vec2 = Vec2::new(0.25, 0.25);
+const HEALTH_PIP_SPACING: f32 = 0.4;
 
-        commands.entity(new_box).insert(EditorSelected);
+const PATROL_BOUND_SIZE: Vec2 = Vec2::new(0.2, 2.);
+const LEDGE_FEELER_SIZE: Vec2 = Vec2::new(0.3, 0.3);
+
+/// An editor enemy as it would be saved
+fn enemy_data(
+    transform: &Transform,
+    enemy: &Enemy,
+    health: &EnemyHealth,
+    mover: Option<&EnemyMover>,
+) -> EnemyData {
+    EnemyData {
+        position: transform.translation.truncate(),
+        behaviour: enemy.0,
+        turn_at_ledges: mover.is_some_and(|mover| mover.turn_at_ledges),
+        patrol_left: mover.and_then(|mover| mover.patrol_left),
+        patrol_right: mover.and_then(|mover| mover.patrol_right),
+        health: health.health,
     }
 }
 
-fn level_editor_input(
-    mut commands: Commands,
-    input: Res<Input<KeyCode>>,
-
-    q_currently_selected: Query<Entity, With<EditorSelected>>,
-) {
-    if let Ok(_e) = q_currently_selected.get_single() {
-        if input.just_pressed(KeyCode::Key1) {
-            // move
-            commands.insert_resource(NextState(EditorTool::Move));
+fn spawn_editor_item(
+    commands: &mut Commands,
+    id: EditorId,
+    item: EditorItem,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) -> Entity {
+    match item {
+        EditorItem::Box(b) => {
+            spawn_editor_box(commands, id, b.tl, b.br, texture_handles, meshes, materials)
         }
-
-        if input.just_pressed(KeyCode::Key2) {
-            // expand
-            commands.insert_resource(NextState(EditorTool::Expand));
+        EditorItem::Enemy(enemy) => spawn_editor_enemy(commands, id, texture_handles, enemy),
+        EditorItem::PlayerSpawn(position) => {
+            spawn_editor_player_spawn(commands, id, texture_handles, position)
         }
-
-        if input.just_pressed(KeyCode::Key3) {
-            // shrink
-            commands.insert_resource(NextState(EditorTool::Shrink));
+        EditorItem::Trigger(trigger) => spawn_editor_trigger(commands, id, trigger),
+        EditorItem::Checkpoint(position) => spawn_editor_checkpoint(commands, id, position),
+        EditorItem::Platform(p) => {
+            spawn_editor_platform(commands, id, p.tl, p.br, texture_handles, meshes, materials)
         }
-    } else {
-        if input.any_just_pressed([KeyCode::Key1, KeyCode::Key2, KeyCode::Key3]) {
-            println!("NOTHING SELECTED!!");
+        EditorItem::MovingPlatform(p) => {
+            spawn_editor_moving_platform(commands, id, &p, texture_handles, meshes, materials)
+        }
+        EditorItem::CrumblingPlatform(p) => {
+            spawn_editor_crumbling_platform(commands, id, p, texture_handles, meshes, materials)
         }
+        EditorItem::Hazard(hazard) => {
+            spawn_editor_hazard(commands, id, hazard, texture_handles, meshes, materials)
+        }
+        EditorItem::Slope(slope) => {
+            spawn_editor_slope(commands, id, slope, texture_handles, meshes, materials)
+        }
+        EditorItem::Boss(position) => spawn_editor_boss(commands, id, texture_handles, position),
     }
+}
 
-    if input.just_pressed(KeyCode::Key0) {
-        deselect(&mut commands, &q_currently_selected);
-        commands.insert_resource(NextState(EditorTool::Select));
-    }
+fn spawn_editor_player_spawn(
+    commands: &mut Commands,
+    id: EditorId,
+    texture_handles: &TextureHandles,
+    position: Vec2,
+) -> Entity {
+    let sprite_size = Some(Vec2::new(PLAYER_RADIUS * 2., PLAYER_RADIUS * 2.));
+
+    commands
+        .spawn(SpriteBundle {
+            texture: texture_handles.char_outline.clone().unwrap(),
+            sprite: Sprite {
+                color: Color::WHITE,
+                custom_size: sprite_size,
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(10.)),
+            ..default()
+        })
+        .insert(PlayerSpawn)
+        .insert(LevelEditorItem)
+        .insert(id)
+        // only here so the mouse can find it
+        .insert(Collider::ball(PLAYER_RADIUS))
+        .insert(Sensor)
+        .with_children(|cb| {
+            cb.spawn(SpriteBundle {
+                texture: texture_handles.char_body.clone().unwrap(),
+                sprite: Sprite {
+                    color: Color::RED,
+                    custom_size: sprite_size,
+                    ..default()
+                },
+                ..default()
+            });
+        })
+        .id()
 }
 
-fn editor_tool_expand(
-    mut q: Query<(&mut Collider, &mut Transform), With<EditorSelected>>,
-    input: Res<Input<KeyCode>>,
+fn spawn_editor_checkpoint(commands: &mut Commands, id: EditorId, position: Vec2) -> Entity {
+    // indices only matter ingame
+    let e = spawn_checkpoint(commands, 0, position);
+    commands.entity(e).insert(LevelEditorItem).insert(id);
+    e
+}
+
+fn spawn_editor_boss(
+    commands: &mut Commands,
+    id: EditorId,
+    texture_handles: &TextureHandles,
+    position: Vec2,
+) -> Entity {
+    let e = spawn_boss(commands, texture_handles, position);
+    // stays where he's put, like editor enemies
+    commands
+        .entity(e)
+        .remove::<KinematicCharacterController>()
+        .remove::<PhysicsInterpolation>()
+        .insert(LevelEditorItem)
+        .insert(id);
+    e
+}
+
+fn spawn_editor_trigger(commands: &mut Commands, id: EditorId, trigger: BoxData) -> Entity {
+    // what kind doesn't matter, playtests always end back in the editor
+    let e = spawn_trigger(commands, trigger, Trigger::Ending);
+    // triggers are invisible ingame, give them a sprite so they can be seen
+    commands
+        .entity(e)
+        .insert(LevelEditorItem)
+        .insert(id)
+        .insert(VisibilityBundle::default())
+        .with_children(|cb| {
+            cb.spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(1., 1., 0., 0.3),
+                    custom_size: Some((trigger.br - trigger.tl).abs()),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(TriggerSprite);
+        });
+    e
+}
+
+fn spawn_editor_level(
+    commands: &mut Commands,
+    level: &LevelData,
+    history: &mut EditorHistory,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
 ) {
-    let (mut collider, mut transform) = q.get_single_mut().unwrap();
-    let he = collider.as_cuboid_mut().unwrap().raw.half_extents;
+    for b in &level.boxes {
+        spawn_editor_box(
+            commands,
+            history.new_id(),
+            b.tl,
+            b.br,
+            texture_handles,
+            meshes,
+            materials,
+        );
+    }
 
-    if input.just_pressed(KeyCode::D) {
-        collider.as_cuboid_mut().unwrap().raw.half_extents = Vector2::new(he.x + 0.5, he.y);
-        transform.translation.x += 0.5;
+    for p in &level.platforms {
+        spawn_editor_platform(
+            commands,
+            history.new_id(),
+            p.tl,
+            p.br,
+            texture_handles,
+            meshes,
+            materials,
+        );
     }
 
-    if input.just_pressed(KeyCode::A) {
-        collider.as_cuboid_mut().unwrap().raw.half_extents = Vector2::new(he.x + 0.5, he.y);
-        transform.translation.x -= 0.5;
+    for p in &level.moving_platforms {
+        spawn_editor_moving_platform(
+            commands,
+            history.new_id(),
+            p,
+            texture_handles,
+            meshes,
+            materials,
+        );
     }
 
-    if input.just_pressed(KeyCode::W) {
-        collider.as_cuboid_mut().unwrap().raw.half_extents = Vector2::new(he.x, he.y + 0.5);
-        transform.translation.y += 0.5;
+    for &p in &level.crumbling_platforms {
+        spawn_editor_crumbling_platform(
+            commands,
+            history.new_id(),
+            p,
+            texture_handles,
+            meshes,
+            materials,
+        );
     }
 
-    if input.just_pressed(KeyCode::S) {
-        collider.as_cuboid_mut().unwrap().raw.half_extents = Vector2::new(he.x, he.y + 0.5);
-        transform.translation.y -= 0.5;
+    for &slope in &level.slopes {
+        spawn_editor_slope(
+            commands,
+            history.new_id(),
+            slope,
+            texture_handles,
+            meshes,
+            materials,
+        );
     }
-}
 
-fn cleanup_level_editor(commands: Commands, q: Query<Entity, With<LevelEditorItem>>) {
-    despawn_with(commands, q);
+    for &hazard in &level.hazards {
+        spawn_editor_hazard(
+            commands,
+            history.new_id(),
+            hazard,
+            texture_handles,
+            meshes,
+            materials,
+        );
+    }
+
+    for enemy in &level.enemies {
+        spawn_editor_enemy(commands, history.new_id(), texture_handles, *enemy);
+    }
+
+    spawn_editor_player_spawn(
+        commands,
+        history.new_id(),
+        texture_handles,
+        level.player_spawn,
+    );
+    spawn_editor_trigger(commands, history.new_id(), level.trigger);
+
+    for &position in &level.checkpoints {
+        spawn_editor_checkpoint(commands, history.new_id(), position);
+    }
+
+    if let Some(position) = level.boss {
+        spawn_editor_boss(commands, history.new_id(), texture_handles, position);
+    }
+}
+
+fn deselect(commands: &mut Commands, q_currently_selected: &Query<Entity, With<EditorSelected>>) {
+    for entity in q_currently_selected {
+        commands.entity(entity).remove::<EditorSelected>();
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_editor_cursor(
+    windows: Res<Windows>,
+    q_camera: Query<(&Transform, &OrthographicProjection), (With<Camera2d>, Without<Crosshair>)>,
+    mut q_crosshair: Query<&mut Transform, (With<Crosshair>, Without<Camera2d>)>,
+    snap_grid: Res<SnapGrid>,
+    mut cursor: ResMut<EditorCursor>,
+) {
+    let window = windows.get_primary().unwrap();
+    let Some(screen_pos) = window.cursor_position() else {
+        return;
+    };
+
+    let (t_cam, projection) = q_camera.single();
+    let window_size = Vec2::new(window.width(), window.height());
+
+    // screen origin is bottom left, camera is looking at the middle of it
+    cursor.world =
+        t_cam.translation.truncate() + (screen_pos - window_size / 2.) * projection.scale;
+    cursor.snapped = snap_grid.snap(cursor.world);
+
+    let mut t_cross = q_crosshair.single_mut();
+    t_cross.translation.x = cursor.snapped.x;
+    t_cross.translation.y = cursor.snapped.y;
+}
+
+const CAMERA_PAN_SPEED: f32 = 20.;
+
+fn pan_camera(
+    mut q_camera: Query<&mut Transform, (With<Camera2d>, Without<Crosshair>)>,
+    input: Res<Input<KeyCode>>,
+    time: Res<Time>,
+) {
+    let mut t_cam = q_camera.single_mut();
+
+    let x = input.pressed(KeyCode::D) as i8 - input.pressed(KeyCode::A) as i8;
+    let y = input.pressed(KeyCode::W) as i8 - input.pressed(KeyCode::S) as i8;
+
+    t_cam.translation +=
+        Vec3::new(x as f32, y as f32, 0.) * CAMERA_PAN_SPEED * time.delta_seconds();
+}
+
+fn snap_grid_input(mut snap_grid: ResMut<SnapGrid>, input: Res<Input<KeyCode>>) {
+    if input.just_pressed(KeyCode::G) {
+        snap_grid.enabled = !snap_grid.enabled;
+    }
+
+    if input.just_pressed(KeyCode::Minus) {
+        snap_grid.size = (snap_grid.size / 2.).max(0.25);
+    }
+
+    if input.just_pressed(KeyCode::Equals) {
+        snap_grid.size = (snap_grid.size * 2.).min(8.);
+    }
+}
+
+fn update_grid_text(snap_grid: Res<SnapGrid>, mut q_grid_text: Query<&mut Text, With<GridText>>) {
+    if let Ok(mut text) = q_grid_text.get_single_mut() {
+        if snap_grid.is_changed() || text.is_added() {
+            text.sections[0].value = if snap_grid.enabled {
+                format!("GRID {}", snap_grid.size)
+            } else {
+                "GRID OFF".into()
+            };
+        }
+    }
+}
+
+// how close to a box edge a click has to be to grab it
+const EDGE_GRAB_DISTANCE: f32 = 0.5;
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn editor_mouse_press(
+    mut commands: Commands,
+    mouse: Res<Input<MouseButton>>,
+    cursor: Res<EditorCursor>,
+    rapier_context: Res<RapierContext>,
+    mut drag: ResMut<EditorDrag>,
+
+    q_selectable: Query<
+        (),
+        (
+            With<LevelEditorItem>,
+            Or<(
+                With<Wall>,
+                With<Enemy>,
+                With<PlayerSpawn>,
+                With<Trigger>,
+                With<Checkpoint>,
+                With<Hazard>,
+                With<Slope>,
+                With<Boss>,
+            )>,
+        ),
+    >,
+    q_parents: Query<&Parent>,
+    q_slopes: Query<(), With<Slope>>,
+    q_boxes: Query<
+        (&BoxTopLeft, &BoxBottomRight),
+        Or<(With<Wall>, With<Trigger>, With<Hazard>, With<Slope>)>,
+    >,
+    q_currently_selected: Query<Entity, With<EditorSelected>>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    // clicks can land on an enemy hitbox or a box edge collider, select their parent instead
+    let mut hit = None;
+    rapier_context.intersections_with_point(cursor.world, QueryFilter::default(), |entity| {
+        // a slope's own collider is its whole box, its click area has the real shape
+        if q_slopes.contains(entity) {
+            return true;
+        }
+
+        hit = if q_selectable.contains(entity) {
+            Some(entity)
+        } else {
+            q_parents
+                .get(entity)
+                .ok()
+                .map(|parent| parent.get())
+                .filter(|parent| q_selectable.contains(*parent))
+        };
+
+        hit.is_none()
+    });
+
+    deselect(&mut commands, &q_currently_selected);
+
+    let Some(entity) = hit else {
+        *drag = EditorDrag::Create {
+            start: cursor.snapped,
+        };
+        return;
+    };
+
+    commands.entity(entity).insert(EditorSelected);
+
+    *drag = if let Ok((tl, br)) = q_boxes.get(entity) {
+        let edges = GrabbedEdges {
+            left: (cursor.world.x - tl.0.x).abs() < EDGE_GRAB_DISTANCE,
+            right: (cursor.world.x - br.0.x).abs() < EDGE_GRAB_DISTANCE,
+            top: (cursor.world.y - tl.0.y).abs() < EDGE_GRAB_DISTANCE,
+            bottom: (cursor.world.y - br.0.y).abs() < EDGE_GRAB_DISTANCE,
+        };
+
+        if edges.left || edges.right || edges.top || edges.bottom {
+            EditorDrag::Resize {
+                entity,
+                edges,
+                before: BoxData { tl: tl.0, br: br.0 },
+            }
+        } else {
+            EditorDrag::None
+        }
+    } else {
+        EditorDrag::None
+    };
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn editor_mouse_drag(
+    mut commands: Commands,
+    mouse: Res<Input<MouseButton>>,
+    cursor: Res<EditorCursor>,
+    snap_grid: Res<SnapGrid>,
+    mut drag: ResMut<EditorDrag>,
+    mut history: ResMut<EditorHistory>,
+
+    mut q_boxes: Query<
+        (
+            &EditorId,
+            &mut Collider,
+            &mut Transform,
+            &mut BoxTopLeft,
+            &mut BoxBottomRight,
+            EditorBoxKind,
+        ),
+        (
+            Or<(With<Wall>, With<Trigger>, With<Hazard>, With<Slope>)>,
+            Without<DragPreview>,
+        ),
+    >,
+    mut q_preview: Query<(Entity, &mut Transform, &mut Sprite), With<DragPreview>>,
+
+    texture_handles: Res<TextureHandles>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    match *drag {
+        EditorDrag::None => {}
+        EditorDrag::Create { start } => {
+            let tl = Vec2::new(start.x.min(cursor.snapped.x), start.y.max(cursor.snapped.y));
+            let br = Vec2::new(start.x.max(cursor.snapped.x), start.y.min(cursor.snapped.y));
+            let size = (br - tl).abs();
+
+            if mouse.just_released(MouseButton::Left) {
+                for (preview, _, _) in &q_preview {
+                    commands.entity(preview).despawn_recursive();
+                }
+
+                // a plain click without dragging out a box just deselects
+                let min_size = if snap_grid.enabled {
+                    snap_grid.size
+                } else {
+                    MIN_HALF_EXTENT * 2.
+                };
+
+                if size.x >= min_size && size.y >= min_size {
+                    let id = history.new_id();
+                    let new_box = spawn_editor_box(
+                        &mut commands,
+                        id,
+                        tl,
+                        br,
+                        &texture_handles,
+                        &mut meshes,
+                        &mut materials,
+                    );
+                    commands.entity(new_box).insert(EditorSelected);
+
+                    history.push(EditorCommand::Spawn {
+                        id,
+                        item: EditorItem::Box(BoxData { tl, br }),
+                    });
+                }
+
+                *drag = EditorDrag::None;
+            } else if let Ok((_, mut transform, mut sprite)) = q_preview.get_single_mut() {
+                transform.translation = ((tl + br) / 2.).extend(11.);
+                sprite.custom_size = Some(size);
+            } else {
+                commands
+                    .spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgba(1., 1., 1., 0.3),
+                            custom_size: Some(size),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(((tl + br) / 2.).extend(11.)),
+                        ..default()
+                    })
+                    .insert(DragPreview)
+                    .insert(LevelEditorItem);
+            }
+        }
+        EditorDrag::Resize {
+            entity,
+            edges,
+            before,
+        } => {
+            let Ok((id, mut collider, mut transform, mut tl, mut br, kind)) =
+                q_boxes.get_mut(entity)
+            else {
+                *drag = EditorDrag::None;
+                return;
+            };
+
+            if mouse.just_released(MouseButton::Left) {
+                // the whole drag is one step in the history
+                let after = BoxData { tl: tl.0, br: br.0 };
+
+                if after != before {
+                    history.push(EditorCommand::Modify {
+                        id: *id,
+                        before: box_item(kind, before),
+                        after: box_item(kind, after),
+                    });
+                }
+
+                *drag = EditorDrag::None;
+                return;
+            }
+
+            let min_size = MIN_HALF_EXTENT * 2.;
+            let mut new_tl = tl.0;
+            let mut new_br = br.0;
+
+            if edges.left {
+                new_tl.x = cursor.snapped.x.min(br.0.x - min_size);
+            }
+
+            if edges.right {
+                new_br.x = cursor.snapped.x.max(tl.0.x + min_size);
+            }
+
+            if edges.top {
+                new_tl.y = cursor.snapped.y.max(br.0.y + min_size);
+            }
+
+            if edges.bottom {
+                new_br.y = cursor.snapped.y.min(tl.0.y - min_size);
+            }
+
+            if new_tl != tl.0 || new_br != br.0 {
+                set_box_corners(new_tl, new_br, &mut collider, &mut transform);
+                tl.0 = new_tl;
+                br.0 = new_br;
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn editor_create_box(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    q_crosshair: Query<&mut Transform, (With<Crosshair>, Without<Camera2d>)>,
+
+    q_currently_selected: Query<Entity, With<EditorSelected>>,
+    mut history: ResMut<EditorHistory>,
+
+    texture_handles: Res<TextureHandles>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let t_cross = q_crosshair.single();
+
+    if input.just_pressed(KeyCode::B) {
+        let id = history.new_id();
+        let tl = Vec2::new(t_cross.translation.x, t_cross.translation.y);
+        let br = Vec2::new(t_cross.translation.x + 1.0, t_cross.translation.y - 1.0);
+
+        let new_box = spawn_editor_box(
+            &mut commands,
+            id,
+            tl,
+            br,
+            &texture_handles,
+            &mut meshes,
+            &mut materials,
+        );
+
+        deselect(&mut commands, &q_currently_selected);
+
+        commands.entity(new_box).insert(EditorSelected);
+
+        history.push(EditorCommand::Spawn {
+            id,
+            item: EditorItem::Box(BoxData { tl, br }),
+        });
+    }
+
+    // O for one-way
+    if input.just_pressed(KeyCode::O) {
+        let id = history.new_id();
+        let tl = Vec2::new(t_cross.translation.x, t_cross.translation.y);
+        let br = tl + Vec2::new(DEFAULT_PLATFORM_SIZE.x, -DEFAULT_PLATFORM_SIZE.y);
+
+        let new_platform = spawn_editor_platform(
+            &mut commands,
+            id,
+            tl,
+            br,
+            &texture_handles,
+            &mut meshes,
+            &mut materials,
+        );
+
+        deselect(&mut commands, &q_currently_selected);
+
+        commands.entity(new_platform).insert(EditorSelected);
+
+        history.push(EditorCommand::Spawn {
+            id,
+            item: EditorItem::Platform(BoxData { tl, br }),
+        });
+    }
+
+    // L for lift, K for... crumbling
+    if input.just_pressed(KeyCode::L) {
+        let id = history.new_id();
+        let tl = Vec2::new(t_cross.translation.x, t_cross.translation.y);
+        let platform = MovingPlatformData {
+            platform: BoxData {
+                tl,
+                br: tl + Vec2::new(DEFAULT_PLATFORM_SIZE.x, -DEFAULT_PLATFORM_SIZE.y),
+            },
+            waypoints: vec![DEFAULT_WAYPOINT],
+            speed: DEFAULT_MOVING_PLATFORM_SPEED,
+        };
+
+        let new_platform = spawn_editor_moving_platform(
+            &mut commands,
+            id,
+            &platform,
+            &texture_handles,
+            &mut meshes,
+            &mut materials,
+        );
+
+        deselect(&mut commands, &q_currently_selected);
+
+        commands.entity(new_platform).insert(EditorSelected);
+
+        history.push(EditorCommand::Spawn {
+            id,
+            item: EditorItem::MovingPlatform(platform),
+        });
+    }
+
+    if input.just_pressed(KeyCode::K) {
+        let id = history.new_id();
+        let tl = Vec2::new(t_cross.translation.x, t_cross.translation.y);
+        let platform = BoxData {
+            tl,
+            br: tl + Vec2::new(DEFAULT_PLATFORM_SIZE.x, -DEFAULT_PLATFORM_SIZE.y),
+        };
+
+        let new_platform = spawn_editor_crumbling_platform(
+            &mut commands,
+            id,
+            platform,
+            &texture_handles,
+            &mut meshes,
+            &mut materials,
+        );
+
+        deselect(&mut commands, &q_currently_selected);
+
+        commands.entity(new_platform).insert(EditorSelected);
+
+        history.push(EditorCommand::Spawn {
+            id,
+            item: EditorItem::CrumblingPlatform(platform),
+        });
+    }
+
+    // H for spikes, X for a kill zone
+    if input.any_just_pressed([KeyCode::H, KeyCode::X]) {
+        let id = history.new_id();
+        let tl = Vec2::new(t_cross.translation.x, t_cross.translation.y);
+        let hazard = HazardData {
+            area: BoxData {
+                tl,
+                br: tl + Vec2::new(DEFAULT_HAZARD_SIZE.x, -DEFAULT_HAZARD_SIZE.y),
+            },
+            kind: if input.just_pressed(KeyCode::H) {
+                HazardKind::Spikes
+            } else {
+                HazardKind::KillZone
+            },
+        };
+
+        let new_hazard = spawn_editor_hazard(
+            &mut commands,
+            id,
+            hazard,
+            &texture_handles,
+            &mut meshes,
+            &mut materials,
+        );
+
+        deselect(&mut commands, &q_currently_selected);
+
+        commands.entity(new_hazard).insert(EditorSelected);
+
+        history.push(EditorCommand::Spawn {
+            id,
+            item: EditorItem::Hazard(hazard),
+        });
+    }
+
+    // / and \ for ramps, R for a rounded hill
+    let shape = if input.just_pressed(KeyCode::Slash) {
+        Some(SlopeShape::RampUp)
+    } else if input.just_pressed(KeyCode::Backslash) {
+        Some(SlopeShape::RampDown)
+    } else if input.just_pressed(KeyCode::R) {
+        Some(SlopeShape::Hill)
+    } else {
+        None
+    };
+
+    if let Some(shape) = shape {
+        let id = history.new_id();
+        let tl = Vec2::new(t_cross.translation.x, t_cross.translation.y);
+        let slope = SlopeData {
+            area: BoxData {
+                tl,
+                br: tl + Vec2::new(DEFAULT_SLOPE_SIZE.x, -DEFAULT_SLOPE_SIZE.y),
+            },
+            shape,
+        };
+
+        let new_slope = spawn_editor_slope(
+            &mut commands,
+            id,
+            slope,
+            &texture_handles,
+            &mut meshes,
+            &mut materials,
+        );
+
+        deselect(&mut commands, &q_currently_selected);
+
+        commands.entity(new_slope).insert(EditorSelected);
+
+        history.push(EditorCommand::Spawn {
+            id,
+            item: EditorItem::Slope(slope),
+        });
+    }
+}
+
+// size of a platform placed with O, L or K, the smallest the shrink tool allows in height
+const DEFAULT_PLATFORM_SIZE: Vec2 = Vec2::new(4., 1.);
+
+// size of a hazard placed with H or X
+const DEFAULT_HAZARD_SIZE: Vec2 = Vec2::new(3., 1.);
+
+// size of a slope placed with /, \ or R
+const DEFAULT_SLOPE_SIZE: Vec2 = Vec2::new(4., 2.);
+
+// path and speed of a moving platform placed with L
+const DEFAULT_WAYPOINT: Vec2 = Vec2::new(6., 0.);
+const DEFAULT_MOVING_PLATFORM_SPEED: f32 = 3.;
+
+/// F toggles whether the selected mover turns at ledges, [ and ] set how far
+/// left and right it patrols to the crosshair. Setting one on the wrong side of
+/// the enemy clears it
+#[allow(clippy::type_complexity)]
+fn editor_edit_patrol(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    q_crosshair: Query<&Transform, (With<Crosshair>, Without<Camera2d>)>,
+    q_selected: Query<
+        (
+            Entity,
+            &EditorId,
+            &Transform,
+            &Enemy,
+            &EnemyHealth,
+            &EnemyMover,
+        ),
+        (With<EditorSelected>, Without<Crosshair>),
+    >,
+    mut history: ResMut<EditorHistory>,
+
+    texture_handles: Res<TextureHandles>,
+) {
+    if !input.any_just_pressed([KeyCode::F, KeyCode::LBracket, KeyCode::RBracket]) {
+        return;
+    }
+
+    let Ok((entity, id, transform, enemy, health, mover)) = q_selected.get_single() else {
+        println!("SELECT A MOVING ENEMY FIRST!!");
+        return;
+    };
+
+    let before = enemy_data(transform, enemy, health, Some(mover));
+    let mut after = before;
+    let offset = q_crosshair.single().translation.x - before.position.x;
+
+    if input.just_pressed(KeyCode::F) {
+        after.turn_at_ledges = !after.turn_at_ledges;
+    }
+    if input.just_pressed(KeyCode::LBracket) {
+        after.patrol_left = Some(-offset).filter(|&left| left > 0.);
+    }
+    if input.just_pressed(KeyCode::RBracket) {
+        after.patrol_right = Some(offset).filter(|&right| right > 0.);
+    }
+
+    // respawned so the markers get rebuilt
+    commands.entity(entity).despawn_recursive();
+    let e = spawn_editor_enemy(&mut commands, *id, &texture_handles, after);
+    commands.entity(e).insert(EditorSelected);
+
+    history.push(EditorCommand::Modify {
+        id: *id,
+        before: EditorItem::Enemy(before),
+        after: EditorItem::Enemy(after),
+    });
+}
+
+// toughest enemy the editor makes, I goes back to one stomp after this
+const MAX_ENEMY_HEALTH: u32 = 5;
+
+/// Tab switches the selected enemy to the next behaviour, I gives it another
+/// stomp of health
+#[allow(clippy::type_complexity)]
+fn editor_edit_enemy(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    q_selected: Query<
+        (
+            Entity,
+            &EditorId,
+            &Transform,
+            &Enemy,
+            &EnemyHealth,
+            Option<&EnemyMover>,
+        ),
+        With<EditorSelected>,
+    >,
+    mut history: ResMut<EditorHistory>,
+
+    texture_handles: Res<TextureHandles>,
+) {
+    if !input.any_just_pressed([KeyCode::Tab, KeyCode::I]) {
+        return;
+    }
+
+    let Ok((entity, id, transform, enemy, health, mover)) = q_selected.get_single() else {
+        println!("SELECT AN ENEMY FIRST!!");
+        return;
+    };
+
+    let before = enemy_data(transform, enemy, health, mover);
+    let mut after = before;
+
+    if input.just_pressed(KeyCode::Tab) {
+        let next = BEHAVIOURS
+            .iter()
+            .position(|&behaviour| behaviour == before.behaviour)
+            .map_or(0, |i| (i + 1) % BEHAVIOURS.len());
+        after.behaviour = BEHAVIOURS[next];
+    }
+    if input.just_pressed(KeyCode::I) {
+        after.health = before.health % MAX_ENEMY_HEALTH + 1;
+    }
+
+    commands.entity(entity).despawn_recursive();
+    let e = spawn_editor_enemy(&mut commands, *id, &texture_handles, after);
+    commands.entity(e).insert(EditorSelected);
+
+    history.push(EditorCommand::Modify {
+        id: *id,
+        before: EditorItem::Enemy(before),
+        after: EditorItem::Enemy(after),
+    });
+}
+
+/// N adds a waypoint at the crosshair to the selected moving platform
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn editor_add_waypoint(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    q_crosshair: Query<&Transform, (With<Crosshair>, Without<Camera2d>)>,
+    q_selected: Query<
+        (
+            Entity,
+            &EditorId,
+            &Transform,
+            &BoxTopLeft,
+            &BoxBottomRight,
+            &MovingPlatform,
+        ),
+        (With<EditorSelected>, Without<Crosshair>),
+    >,
+    mut history: ResMut<EditorHistory>,
+
+    texture_handles: Res<TextureHandles>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !input.just_pressed(KeyCode::N) {
+        return;
+    }
+
+    let Ok((entity, id, transform, tl, br, platform)) = q_selected.get_single() else {
+        println!("SELECT A MOVING PLATFORM FIRST!!");
+        return;
+    };
+
+    let cursor = q_crosshair.single().translation.truncate();
+    let before = MovingPlatformData {
+        platform: BoxData { tl: tl.0, br: br.0 },
+        waypoints: platform.waypoints.clone(),
+        speed: platform.speed,
+    };
+    let mut after = before.clone();
+    after
+        .waypoints
+        .push(cursor - transform.translation.truncate());
+
+    // respawned so the markers get rebuilt
+    commands.entity(entity).despawn_recursive();
+    let e = spawn_editor_moving_platform(
+        &mut commands,
+        *id,
+        &after,
+        &texture_handles,
+        &mut meshes,
+        &mut materials,
+    );
+    commands.entity(e).insert(EditorSelected);
+
+    history.push(EditorCommand::Modify {
+        id: *id,
+        before: EditorItem::MovingPlatform(before),
+        after: EditorItem::MovingPlatform(after),
+    });
+}
+
+// size of a trigger placed when the level doesn't have one
+const DEFAULT_TRIGGER_SIZE: Vec2 = Vec2::new(4., 2.);
+
+/// E and M place static and moving enemies (Tab changes what they do), C places a checkpoint, P and T move
+/// the player spawn and trigger to the crosshair, creating them if they were deleted. U does
+/// the same for Mr Fuqheed, who levels don't need
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn editor_place_items(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    q_crosshair: Query<&Transform, (With<Crosshair>, Without<Camera2d>)>,
+
+    q_currently_selected: Query<Entity, With<EditorSelected>>,
+    mut q_spawn: Query<(&EditorId, &mut Transform), (With<PlayerSpawn>, Without<Crosshair>)>,
+    mut q_boss: Query<
+        (&EditorId, &mut Transform),
+        (
+            With<Boss>,
+            With<LevelEditorItem>,
+            Without<PlayerSpawn>,
+            Without<Trigger>,
+            Without<Crosshair>,
+        ),
+    >,
+    mut q_trigger: Query<
+        (
+            &EditorId,
+            &mut Transform,
+            &mut BoxTopLeft,
+            &mut BoxBottomRight,
+        ),
+        (
+            With<Trigger>,
+            With<LevelEditorItem>,
+            Without<PlayerSpawn>,
+            Without<Crosshair>,
+        ),
+    >,
+    mut history: ResMut<EditorHistory>,
+
+    texture_handles: Res<TextureHandles>,
+) {
+    let cursor = q_crosshair.single().translation.truncate();
+    // actors stand on the crosshair rather than sinking into the floor
+    let feet = cursor + Vec2::Y * PLAYER_RADIUS;
+
+    if input.any_just_pressed([KeyCode::E, KeyCode::M]) {
+        let id = history.new_id();
+        let enemy = EnemyData {
+            position: feet,
+            behaviour: if input.just_pressed(KeyCode::M) {
+                EnemyBehaviour::Mover
+            } else {
+                EnemyBehaviour::Static
+            },
+            turn_at_ledges: false,
+            patrol_left: None,
+            patrol_right: None,
+            health: 1,
+        };
+
+        let e = spawn_editor_enemy(&mut commands, id, &texture_handles, enemy);
+
+        deselect(&mut commands, &q_currently_selected);
+        commands.entity(e).insert(EditorSelected);
+
+        history.push(EditorCommand::Spawn {
+            id,
+            item: EditorItem::Enemy(enemy),
+        });
+    }
+
+    if input.just_pressed(KeyCode::C) {
+        let id = history.new_id();
+        let e = spawn_editor_checkpoint(&mut commands, id, feet);
+
+        deselect(&mut commands, &q_currently_selected);
+        commands.entity(e).insert(EditorSelected);
+
+        history.push(EditorCommand::Spawn {
+            id,
+            item: EditorItem::Checkpoint(feet),
+        });
+    }
+
+    if input.just_pressed(KeyCode::P) {
+        if let Ok((id, mut transform)) = q_spawn.get_single_mut() {
+            let before = transform.translation.truncate();
+
+            if before != feet {
+                transform.translation.x = feet.x;
+                transform.translation.y = feet.y;
+
+                history.push(EditorCommand::Modify {
+                    id: *id,
+                    before: EditorItem::PlayerSpawn(before),
+                    after: EditorItem::PlayerSpawn(feet),
+                });
+            }
+        } else {
+            let id = history.new_id();
+            spawn_editor_player_spawn(&mut commands, id, &texture_handles, feet);
+
+            history.push(EditorCommand::Spawn {
+                id,
+                item: EditorItem::PlayerSpawn(feet),
+            });
+        }
+    }
+
+    if input.just_pressed(KeyCode::U) {
+        let position = cursor + Vec2::Y * BOSS_RADIUS;
+
+        if let Ok((id, mut transform)) = q_boss.get_single_mut() {
+            let before = transform.translation.truncate();
+
+            if before != position {
+                transform.translation.x = position.x;
+                transform.translation.y = position.y;
+
+                history.push(EditorCommand::Modify {
+                    id: *id,
+                    before: EditorItem::Boss(before),
+                    after: EditorItem::Boss(position),
+                });
+            }
+        } else {
+            let id = history.new_id();
+            spawn_editor_boss(&mut commands, id, &texture_handles, position);
+
+            history.push(EditorCommand::Spawn {
+                id,
+                item: EditorItem::Boss(position),
+            });
+        }
+    }
+
+    if input.just_pressed(KeyCode::T) {
+        if let Ok((id, mut transform, mut tl, mut br)) = q_trigger.get_single_mut() {
+            // keep the size, centre it on the crosshair
+            let offset = cursor - (tl.0 + br.0) / 2.;
+
+            if offset != Vec2::ZERO {
+                let before = BoxData { tl: tl.0, br: br.0 };
+
+                tl.0 += offset;
+                br.0 += offset;
+                transform.translation.x = cursor.x;
+                transform.translation.y = cursor.y;
+
+                history.push(EditorCommand::Modify {
+                    id: *id,
+                    before: EditorItem::Trigger(before),
+                    after: EditorItem::Trigger(BoxData { tl: tl.0, br: br.0 }),
+                });
+            }
+        } else {
+            let id = history.new_id();
+            let half = DEFAULT_TRIGGER_SIZE / 2.;
+            let trigger = BoxData {
+                tl: cursor + Vec2::new(-half.x, half.y),
+                br: cursor + Vec2::new(half.x, -half.y),
+            };
+
+            spawn_editor_trigger(&mut commands, id, trigger);
+
+            history.push(EditorCommand::Spawn {
+                id,
+                item: EditorItem::Trigger(trigger),
+            });
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn resize_trigger_sprite(
+    q_triggers: Query<
+        (&BoxTopLeft, &BoxBottomRight, &Children),
+        (
+            With<Trigger>,
+            Or<(Changed<BoxTopLeft>, Changed<BoxBottomRight>)>,
+        ),
+    >,
+    mut q_sprites: Query<&mut Sprite, With<TriggerSprite>>,
+) {
+    for (tl, br, children) in &q_triggers {
+        for &child in children {
+            if let Ok(mut sprite) = q_sprites.get_mut(child) {
+                sprite.custom_size = Some((br.0 - tl.0).abs());
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn resize_slope_click_area(
+    q_slopes: Query<
+        (&Slope, &BoxTopLeft, &BoxBottomRight, &Children),
+        Or<(Changed<BoxTopLeft>, Changed<BoxBottomRight>)>,
+    >,
+    mut q_areas: Query<&mut Collider, With<SlopeClickArea>>,
+) {
+    for (slope, tl, br, children) in &q_slopes {
+        for &child in children {
+            if let Ok(mut collider) = q_areas.get_mut(child) {
+                *collider = slope_fill_collider(SlopeData {
+                    area: BoxData { tl: tl.0, br: br.0 },
+                    shape: slope.0,
+                });
+            }
+        }
+    }
+}
+
+// padding around the selection so the outline shows past the item
+const HIGHLIGHT_MARGIN: f32 = 0.4;
+
+#[allow(clippy::type_complexity)]
+fn show_selected(
+    q_selected: Query<
+        (
+            &GlobalTransform,
+            Option<&BoxTopLeft>,
+            Option<&BoxBottomRight>,
+        ),
+        With<EditorSelected>,
+    >,
+    mut q_highlight: Query<
+        (&mut Transform, &mut Sprite, &mut Visibility),
+        With<SelectionHighlight>,
+    >,
+) {
+    let Ok((mut transform, mut sprite, mut visibility)) = q_highlight.get_single_mut() else {
+        return;
+    };
+
+    let Ok((global_transform, tl, br)) = q_selected.get_single() else {
+        if visibility.is_visible {
+            visibility.is_visible = false;
+        }
+        return;
+    };
+
+    let size = match (tl, br) {
+        (Some(tl), Some(br)) => (br.0 - tl.0).abs(),
+        // enemies and the player spawn are player sized
+        _ => Vec2::splat(PLAYER_RADIUS * 2.),
+    };
+
+    let size = size + Vec2::splat(HIGHLIGHT_MARGIN * 2.);
+    let position = global_transform.translation().truncate().extend(12.);
+
+    if sprite.custom_size != Some(size) {
+        sprite.custom_size = Some(size);
+    }
+
+    if transform.translation != position {
+        transform.translation = position;
+    }
+
+    if !visibility.is_visible {
+        visibility.is_visible = true;
+    }
+}
+
+fn level_editor_input(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+
+    q_currently_selected: Query<Entity, With<EditorSelected>>,
+) {
+    if let Ok(_e) = q_currently_selected.get_single() {
+        if input.just_pressed(KeyCode::Key1) {
+            // move
+            commands.insert_resource(NextState(EditorTool::Move));
+        }
+
+        if input.just_pressed(KeyCode::Key2) {
+            // expand
+            commands.insert_resource(NextState(EditorTool::Expand));
+        }
+
+        if input.just_pressed(KeyCode::Key3) {
+            // shrink
+            commands.insert_resource(NextState(EditorTool::Shrink));
+        }
+    } else {
+        if input.any_just_pressed([KeyCode::Key1, KeyCode::Key2, KeyCode::Key3]) {
+            println!("NOTHING SELECTED!!");
+        }
+    }
+
+    if input.just_pressed(KeyCode::Key0) {
+        deselect(&mut commands, &q_currently_selected);
+        commands.insert_resource(NextState(EditorTool::Select));
+    }
+}
+
+// smallest half extent the shrink tool will leave a box with
+const MIN_HALF_EXTENT: f32 = 0.5;
+
+/// Components that tell apart the different things the box tools work on
+type EditorBoxKind<'a> = (
+    Option<&'a Trigger>,
+    Option<&'a OneWayPlatform>,
+    Option<&'a MovingPlatform>,
+    Option<&'a CrumblingPlatform>,
+    Option<&'a Hazard>,
+    Option<&'a Slope>,
+);
+
+// triggers and platforms share the box tools, but have to come back as themselves on undo
+fn box_item(
+    (trigger, platform, moving, crumbling, hazard, slope): EditorBoxKind,
+    b: BoxData,
+) -> EditorItem {
+    if trigger.is_some() {
+        EditorItem::Trigger(b)
+    } else if let Some(slope) = slope {
+        EditorItem::Slope(SlopeData {
+            area: b,
+            shape: slope.0,
+        })
+    } else if let Some(hazard) = hazard {
+        EditorItem::Hazard(HazardData {
+            area: b,
+            kind: hazard.0,
+        })
+    } else if platform.is_some() {
+        EditorItem::Platform(b)
+    } else if let Some(moving) = moving {
+        EditorItem::MovingPlatform(MovingPlatformData {
+            platform: b,
+            waypoints: moving.waypoints.clone(),
+            speed: moving.speed,
+        })
+    } else if crumbling.is_some() {
+        EditorItem::CrumblingPlatform(b)
+    } else {
+        EditorItem::Box(b)
+    }
+}
+
+/// Update a box's corners after a tool has changed it, recording the change for undo
+fn sync_box_corners(
+    id: EditorId,
+    kind: EditorBoxKind,
+    collider: &Collider,
+    transform: &Transform,
+    tl: &mut Mut<BoxTopLeft>,
+    br: &mut Mut<BoxBottomRight>,
+    history: &mut EditorHistory,
+) {
+    let (new_tl, new_br) = box_corners(collider, transform);
+
+    // only write on change, so change detection means something
+    if tl.0 != new_tl || br.0 != new_br {
+        history.push(EditorCommand::Modify {
+            id,
+            before: box_item(kind, BoxData { tl: tl.0, br: br.0 }),
+            after: box_item(
+                kind,
+                BoxData {
+                    tl: new_tl,
+                    br: new_br,
+                },
+            ),
+        });
+
+        tl.0 = new_tl;
+        br.0 = new_br;
+    }
+}
+
+// inverse of sync_box_corners, for tools that work on corners
+fn set_box_corners(tl: Vec2, br: Vec2, collider: &mut Collider, transform: &mut Transform) {
+    let he = ((br - tl) / 2.).abs();
+    let center = (tl + br) / 2.;
+
+    collider.as_cuboid_mut().unwrap().raw.half_extents = Vector2::new(he.x, he.y);
+    transform.translation.x = center.x;
+    transform.translation.y = center.y;
+}
+
+#[allow(clippy::type_complexity)]
+fn editor_tool_expand(
+    mut q: Query<
+        (
+            &EditorId,
+            &mut Collider,
+            &mut Transform,
+            &mut BoxTopLeft,
+            &mut BoxBottomRight,
+            EditorBoxKind,
+        ),
+        With<EditorSelected>,
+    >,
+    input: Res<Input<KeyCode>>,
+    mut history: ResMut<EditorHistory>,
+) {
+    let Ok((id, mut collider, mut transform, mut tl, mut br, kind)) = q.get_single_mut() else {
+        return;
+    };
+    let he = collider.as_cuboid().unwrap().raw.half_extents;
+
+    if input.just_pressed(KeyCode::D) {
+        collider.as_cuboid_mut().unwrap().raw.half_extents = Vector2::new(he.x + 0.5, he.y);
+        transform.translation.x += 0.5;
+    }
+
+    if input.just_pressed(KeyCode::A) {
+        collider.as_cuboid_mut().unwrap().raw.half_extents = Vector2::new(he.x + 0.5, he.y);
+        transform.translation.x -= 0.5;
+    }
+
+    if input.just_pressed(KeyCode::W) {
+        collider.as_cuboid_mut().unwrap().raw.half_extents = Vector2::new(he.x, he.y + 0.5);
+        transform.translation.y += 0.5;
+    }
+
+    if input.just_pressed(KeyCode::S) {
+        collider.as_cuboid_mut().unwrap().raw.half_extents = Vector2::new(he.x, he.y + 0.5);
+        transform.translation.y -= 0.5;
+    }
+
+    sync_box_corners(
+        *id,
+        kind,
+        &collider,
+        &transform,
+        &mut tl,
+        &mut br,
+        &mut history,
+    );
+}
+
+#[allow(clippy::type_complexity)]
+fn editor_tool_move(
+    mut q: Query<
+        (
+            &EditorId,
+            &Collider,
+            &mut Transform,
+            &mut BoxTopLeft,
+            &mut BoxBottomRight,
+            EditorBoxKind,
+        ),
+        With<EditorSelected>,
+    >,
+    input: Res<Input<KeyCode>>,
+    mut history: ResMut<EditorHistory>,
+) {
+    let Ok((id, collider, mut transform, mut tl, mut br, kind)) = q.get_single_mut() else {
+        return;
+    };
+
+    if input.just_pressed(KeyCode::D) {
+        transform.translation.x += 1.;
+    }
+
+    if input.just_pressed(KeyCode::A) {
+        transform.translation.x -= 1.;
+    }
+
+    if input.just_pressed(KeyCode::W) {
+        transform.translation.y += 1.;
+    }
+
+    if input.just_pressed(KeyCode::S) {
+        transform.translation.y -= 1.;
+    }
+
+    sync_box_corners(
+        *id,
+        kind,
+        collider,
+        &transform,
+        &mut tl,
+        &mut br,
+        &mut history,
+    );
+}
+
+// pulls the edge in the pressed direction back towards the middle
+#[allow(clippy::type_complexity)]
+fn editor_tool_shrink(
+    mut q: Query<
+        (
+            &EditorId,
+            &mut Collider,
+            &mut Transform,
+            &mut BoxTopLeft,
+            &mut BoxBottomRight,
+            EditorBoxKind,
+        ),
+        With<EditorSelected>,
+    >,
+    input: Res<Input<KeyCode>>,
+    mut history: ResMut<EditorHistory>,
+) {
+    let Ok((id, mut collider, mut transform, mut tl, mut br, kind)) = q.get_single_mut() else {
+        return;
+    };
+    let he = collider.as_cuboid().unwrap().raw.half_extents;
+
+    let can_shrink_x = he.x - 0.5 >= MIN_HALF_EXTENT;
+    let can_shrink_y = he.y - 0.5 >= MIN_HALF_EXTENT;
+
+    if input.just_pressed(KeyCode::D) && can_shrink_x {
+        collider.as_cuboid_mut().unwrap().raw.half_extents = Vector2::new(he.x - 0.5, he.y);
+        transform.translation.x -= 0.5;
+    }
+
+    if input.just_pressed(KeyCode::A) && can_shrink_x {
+        collider.as_cuboid_mut().unwrap().raw.half_extents = Vector2::new(he.x - 0.5, he.y);
+        transform.translation.x += 0.5;
+    }
+
+    if input.just_pressed(KeyCode::W) && can_shrink_y {
+        collider.as_cuboid_mut().unwrap().raw.half_extents = Vector2::new(he.x, he.y - 0.5);
+        transform.translation.y -= 0.5;
+    }
+
+    if input.just_pressed(KeyCode::S) && can_shrink_y {
+        collider.as_cuboid_mut().unwrap().raw.half_extents = Vector2::new(he.x, he.y - 0.5);
+        transform.translation.y += 0.5;
+    }
+
+    sync_box_corners(
+        *id,
+        kind,
+        &collider,
+        &transform,
+        &mut tl,
+        &mut br,
+        &mut history,
+    );
+}
+
+#[allow(clippy::type_complexity)]
+fn editor_delete_selected(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut history: ResMut<EditorHistory>,
+
+    q_boxes: Query<
+        (
+            Entity,
+            &EditorId,
+            &BoxTopLeft,
+            &BoxBottomRight,
+            EditorBoxKind,
+        ),
+        With<EditorSelected>,
+    >,
+    q_enemies: Query<
+        (
+            Entity,
+            &EditorId,
+            &Transform,
+            &Enemy,
+            &EnemyHealth,
+            Option<&EnemyMover>,
+        ),
+        With<EditorSelected>,
+    >,
+    q_points: Query<
+        (
+            Entity,
+            &EditorId,
+            &Transform,
+            Option<&Checkpoint>,
+            Option<&Boss>,
+        ),
+        (
+            Or<(With<PlayerSpawn>, With<Checkpoint>, With<Boss>)>,
+            With<EditorSelected>,
+        ),
+    >,
+) {
+    if !input.any_just_pressed([KeyCode::Delete, KeyCode::Back]) {
+        return;
+    }
+
+    // saving refuses a level without a spawn or trigger, so these can go too
+    for (entity, id, tl, br, kind) in &q_boxes {
+        commands.entity(entity).despawn_recursive();
+        history.push(EditorCommand::Despawn {
+            id: *id,
+            item: box_item(kind, BoxData { tl: tl.0, br: br.0 }),
+        });
+    }
+
+    for (entity, id, transform, checkpoint, boss) in &q_points {
+        commands.entity(entity).despawn_recursive();
+        history.push(EditorCommand::Despawn {
+            id: *id,
+            item: point_item_kind(checkpoint, boss)(transform.translation.truncate()),
+        });
+    }
+
+    for (entity, id, transform, enemy, health, mover) in &q_enemies {
+        commands.entity(entity).despawn_recursive();
+        history.push(EditorCommand::Despawn {
+            id: *id,
+            item: EditorItem::Enemy(enemy_data(transform, enemy, health, mover)),
+        });
+    }
+
+    commands.insert_resource(NextState(EditorTool::Select));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn editor_undo_redo(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut history: ResMut<EditorHistory>,
+    mut drag: ResMut<EditorDrag>,
+
+    q_items: Query<(Entity, &EditorId)>,
+    q_currently_selected: Query<Entity, With<EditorSelected>>,
+
+    texture_handles: Res<TextureHandles>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !input.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
+        return;
+    }
+
+    let command = if input.just_pressed(KeyCode::Z) {
+        history.undo()
+    } else if input.just_pressed(KeyCode::Y) {
+        history.redo()
+    } else {
+        return;
+    };
+
+    let Some(command) = command else {
+        println!("NOTHING TO UNDO/REDO!!");
+        return;
+    };
+
+    // selection and drags may point at entities that are about to be respawned
+    deselect(&mut commands, &q_currently_selected);
+    *drag = EditorDrag::None;
+    commands.insert_resource(NextState(EditorTool::Select));
+
+    let despawn_id = |commands: &mut Commands, id: EditorId| {
+        for (entity, item_id) in &q_items {
+            if *item_id == id {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    };
+
+    match command {
+        EditorCommand::Spawn { id, item } => {
+            spawn_editor_item(
+                &mut commands,
+                id,
+                item,
+                &texture_handles,
+                &mut meshes,
+                &mut materials,
+            );
+        }
+        EditorCommand::Despawn { id, .. } => despawn_id(&mut commands, id),
+        EditorCommand::Modify { id, after, .. } => {
+            despawn_id(&mut commands, id);
+            spawn_editor_item(
+                &mut commands,
+                id,
+                after,
+                &texture_handles,
+                &mut meshes,
+                &mut materials,
+            );
+        }
+    }
+}
+
+fn cleanup_level_editor(commands: Commands, q: Query<Entity, With<LevelEditorItem>>) {
+    despawn_with(commands, q);
+}
+
+fn setup_editor_menu(mut commands: Commands, ui_font: Res<UiFont>) {
+    // text
+    commands
+        .spawn(TextBundle {
+            text: Text::from_section(
+                "editor",
+                TextStyle {
+                    font: ui_font.0.clone(),
+                    font_size: 40.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            ),
+            style: Style {
+                size: Size::new(Val::Px(150.0), Val::Px(65.0)),
+                // center button
+                margin: UiRect::all(Val::Auto),
+                // horizontally center child text
+                justify_content: JustifyContent::Center,
+                // vertically center child text
+                align_items: AlignItems::FlexStart,
+
+                position: UiRect {
+                    left: Val::Percent(35.),
+                    top: Val::Px(100.),
+                    ..default()
+                },
+
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(EditorMenuItem);
+
+    spawn_editor_menu_button(&mut commands, &ui_font, "SAVE", SaveButton);
+    spawn_editor_menu_button(&mut commands, &ui_font, "LOAD", LoadButton);
+    spawn_editor_menu_button(&mut commands, &ui_font, "MENU", MenuButton);
+    spawn_editor_menu_button(&mut commands, &ui_font, "QUIT", QuitButton);
+}
+
+fn spawn_editor_menu_button(
+    commands: &mut Commands,
+    ui_font: &UiFont,
+    label: &str,
+    button: impl Component,
+) {
+    commands
+        .spawn(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(150.0), Val::Px(65.0)),
+                // center button
+                margin: UiRect::all(Val::Auto),
+                // horizontally center child text
+                justify_content: JustifyContent::Center,
+                // vertically center child text
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::rgb(128., 0., 0.).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font: ui_font.0.clone(),
+                    font_size: 40.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            ));
+        })
+        .insert(EditorMenuItem)
+        .insert(button);
+}
+
+type EditorBoxQuery<'a> = (
+    &'a EditorId,
+    &'a BoxTopLeft,
+    &'a BoxBottomRight,
+    EditorBoxKind<'a>,
+);
+type EditorEnemyQuery<'a> = (
+    &'a EditorId,
+    &'a Transform,
+    &'a Enemy,
+    &'a EnemyHealth,
+    Option<&'a EnemyMover>,
+);
+type EditorPointQuery<'a> = (
+    &'a EditorId,
+    &'a Transform,
+    Option<&'a Checkpoint>,
+    Option<&'a Boss>,
+);
+
+// player spawns, checkpoints and the boss are all just a position
+fn point_item_kind(checkpoint: Option<&Checkpoint>, boss: Option<&Boss>) -> fn(Vec2) -> EditorItem {
+    match (checkpoint, boss) {
+        (Some(_), _) => EditorItem::Checkpoint,
+        (_, Some(_)) => EditorItem::Boss,
+        _ => EditorItem::PlayerSpawn,
+    }
+}
+
+/// Everything placed in the editor, as it would be respawned by undo
+#[allow(clippy::type_complexity)]
+fn editor_items(
+    q_boxes: &Query<EditorBoxQuery, With<LevelEditorItem>>,
+    q_enemies: &Query<EditorEnemyQuery, (With<Enemy>, With<LevelEditorItem>)>,
+    q_points: &Query<EditorPointQuery, Or<(With<PlayerSpawn>, With<Checkpoint>, With<Boss>)>>,
+) -> Vec<(EditorId, EditorItem)> {
+    let boxes = q_boxes
+        .iter()
+        .map(|(id, tl, br, kind)| (*id, box_item(kind, BoxData { tl: tl.0, br: br.0 })));
+
+    let enemies = q_enemies
+        .iter()
+        .map(|(id, transform, enemy, health, mover)| {
+            (
+                *id,
+                EditorItem::Enemy(enemy_data(transform, enemy, health, mover)),
+            )
+        });
+
+    let points = q_points.iter().map(|(id, transform, checkpoint, boss)| {
+        (
+            *id,
+            point_item_kind(checkpoint, boss)(transform.translation.truncate()),
+        )
+    });
+
+    boxes.chain(enemies).chain(points).collect()
+}
+
+/// `None` unless there's exactly one player spawn and one trigger
+fn level_from_items(items: &[(EditorId, EditorItem)], death_plane: f32) -> Option<LevelData> {
+    let mut player_spawns = vec![];
+    let mut triggers = vec![];
+    let mut boxes = vec![];
+    let mut platforms = vec![];
+    let mut moving_platforms = vec![];
+    let mut crumbling_platforms = vec![];
+    let mut hazards = vec![];
+    let mut slopes = vec![];
+    let mut enemies = vec![];
+    let mut checkpoints = vec![];
+    let mut bosses = vec![];
+
+    for (_id, item) in items {
+        match item.clone() {
+            EditorItem::Box(b) => boxes.push(b),
+            EditorItem::Enemy(enemy) => enemies.push(enemy),
+            EditorItem::PlayerSpawn(position) => player_spawns.push(position),
+            EditorItem::Trigger(trigger) => triggers.push(trigger),
+            EditorItem::Checkpoint(position) => checkpoints.push(position),
+            EditorItem::Platform(p) => platforms.push(p),
+            EditorItem::MovingPlatform(p) => moving_platforms.push(p),
+            EditorItem::CrumblingPlatform(p) => crumbling_platforms.push(p),
+            EditorItem::Hazard(hazard) => hazards.push(hazard),
+            EditorItem::Slope(slope) => slopes.push(slope),
+            EditorItem::Boss(position) => bosses.push(position),
+        }
+    }
+
+    let (&[player_spawn], &[trigger]) = (&player_spawns[..], &triggers[..]) else {
+        return None;
+    };
+
+    Some(LevelData {
+        player_spawn,
+        boxes,
+        platforms,
+        moving_platforms,
+        crumbling_platforms,
+        slopes,
+        hazards,
+        enemy_count: enemies.len(),
+        enemies,
+        boss: bosses.first().copied(),
+        trigger,
+        checkpoints,
+        death_plane,
+    })
+}
+
+/// Enter playtests the current layout, which comes back when the playtest ends
+#[allow(clippy::type_complexity)]
+fn editor_playtest(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+
+    q_boxes: Query<EditorBoxQuery, With<LevelEditorItem>>,
+    q_enemies: Query<EditorEnemyQuery, (With<Enemy>, With<LevelEditorItem>)>,
+    q_points: Query<EditorPointQuery, Or<(With<PlayerSpawn>, With<Checkpoint>, With<Boss>)>>,
+    q_camera: Query<&Transform, With<Camera2d>>,
+    death_plane: Res<DeathPlane>,
+) {
+    if !input.just_pressed(KeyCode::Return) {
+        return;
+    }
+
+    let items = editor_items(&q_boxes, &q_enemies, &q_points);
+    let Some(level) = level_from_items(&items, death_plane.0) else {
+        println!("LEVEL NEEDS ONE PLAYER SPAWN AND ONE TRIGGER!!");
+        return;
+    };
+
+    commands.insert_resource(Playtest {
+        level,
+        items,
+        camera: q_camera.single().translation,
+    });
+    commands.insert_resource(NextState(GameState::InGame));
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn save_button(
+    mut commands: Commands,
+    button_query: Query<&Interaction, (Changed<Interaction>, With<SaveButton>)>,
+
+    q_boxes: Query<EditorBoxQuery, With<LevelEditorItem>>,
+    q_enemies: Query<EditorEnemyQuery, (With<Enemy>, With<LevelEditorItem>)>,
+    q_points: Query<EditorPointQuery, Or<(With<PlayerSpawn>, With<Checkpoint>, With<Boss>)>>,
+    death_plane: Res<DeathPlane>,
+
+    audio: Res<Audio>,
+    sound_collection: Res<SoundCollection>,
+    audio_volume: Res<AudioVolume>,
+) {
+    for interact in &button_query {
+        match *interact {
+            Interaction::Clicked => {
+                audio.play_with_settings(
+                    sound_collection.beep.clone(),
+                    PlaybackSettings::ONCE.with_volume(audio_volume.0),
+                );
+
+                let items = editor_items(&q_boxes, &q_enemies, &q_points);
+                let Some(level) = level_from_items(&items, death_plane.0) else {
+                    println!("LEVEL NEEDS ONE PLAYER SPAWN AND ONE TRIGGER!!");
+                    continue;
+                };
+
+                match write_level_file(EDITOR_LEVEL_PATH, &level) {
+                    Ok(()) => println!("saved level to {EDITOR_LEVEL_PATH}"),
+                    Err(e) => println!("couldn't save level: {e}"),
+                }
+
+                commands.insert_resource(NextState(PauseState::Running));
+            }
+            Interaction::Hovered => {}
+            Interaction::None => {}
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn load_button(
+    mut commands: Commands,
+    button_query: Query<&Interaction, (Changed<Interaction>, With<LoadButton>)>,
+
+    q_level_items: Query<
+        Entity,
+        (
+            With<LevelEditorItem>,
+            Or<(
+                With<Wall>,
+                With<Enemy>,
+                With<PlayerSpawn>,
+                With<Trigger>,
+                With<Checkpoint>,
+                With<Hazard>,
+                With<Slope>,
+                With<Boss>,
+            )>,
+        ),
+    >,
+
+    mut history: ResMut<EditorHistory>,
+
+    texture_handles: Res<TextureHandles>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+
+    audio: Res<Audio>,
+    sound_collection: Res<SoundCollection>,
+    audio_volume: Res<AudioVolume>,
+) {
+    for interact in &button_query {
+        match *interact {
+            Interaction::Clicked => {
+                audio.play_with_settings(
+                    sound_collection.beep.clone(),
+                    PlaybackSettings::ONCE.with_volume(audio_volume.0),
+                );
+
+                let level = match read_level_file(EDITOR_LEVEL_PATH) {
+                    Ok(level) => level,
+                    Err(e) => {
+                        println!("couldn't load level: {e}");
+                        continue;
+                    }
+                };
+
+                for entity in &q_level_items {
+                    commands.entity(entity).despawn_recursive();
+                }
+
+                // can't undo past a load
+                history.clear();
+                commands.insert_resource(DeathPlane(level.death_plane));
+
+                spawn_editor_level(
+                    &mut commands,
+                    &level,
+                    &mut history,
+                    &texture_handles,
+                    &mut meshes,
+                    &mut materials,
+                );
+
+                commands.insert_resource(NextState(EditorTool::Select));
+                commands.insert_resource(NextState(PauseState::Running));
+            }
+            Interaction::Hovered => {}
+            Interaction::None => {}
+        }
+    }
 }
@@ -0,0 +1,249 @@
+use std::{error::Error, fs, path::Path};
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Levels in the order they're played. Each level's trigger leads on to the
+/// next, the last one's to the endings
+pub const LEVEL_PATHS: [&str; 2] = ["levels/course.level.ron", "levels/finale.level.ron"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoxData {
+    pub tl: Vec2,
+    pub br: Vec2,
+}
+
+/// What an enemy does, see `enemy::behaviour_entry` for what each one gets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum EnemyBehaviour {
+    /// stands still
+    #[default]
+    Static,
+    /// walks back and forth
+    Mover,
+    /// runs at the player when it can see them
+    Chaser,
+    /// hops up and down
+    Jumper,
+    /// floats back and forth along a wave, ignoring gravity
+    Flyer,
+    /// stands still and throws laughs at the player
+    Shooter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SavedEnemyData")]
+pub struct EnemyData {
+    pub position: Vec2,
+    pub behaviour: EnemyBehaviour,
+    /// movers look for ledges ahead and turn around instead of walking off
+    pub turn_at_ledges: bool,
+    /// how far left of `position` movers and flyers go before turning around
+    pub patrol_left: Option<f32>,
+    /// how far right of `position` movers and flyers go before turning around
+    pub patrol_right: Option<f32>,
+    /// stomps it takes to kill
+    pub health: u32,
+}
+
+/// `EnemyData` as levels store it, including ones saved before behaviours
+/// replaced the `mover` flag
+#[derive(Deserialize)]
+struct SavedEnemyData {
+    position: Vec2,
+    // written without the `Some`, missing in older levels
+    #[serde(default, deserialize_with = "deserialize_some")]
+    behaviour: Option<EnemyBehaviour>,
+    #[serde(default)]
+    mover: bool,
+    #[serde(default)]
+    turn_at_ledges: bool,
+    #[serde(default)]
+    patrol_left: Option<f32>,
+    #[serde(default)]
+    patrol_right: Option<f32>,
+    #[serde(default = "default_enemy_health")]
+    health: u32,
+}
+
+impl From<SavedEnemyData> for EnemyData {
+    fn from(saved: SavedEnemyData) -> Self {
+        let behaviour = saved.behaviour.unwrap_or(if saved.mover {
+            EnemyBehaviour::Mover
+        } else {
+            EnemyBehaviour::Static
+        });
+
+        Self {
+            position: saved.position,
+            behaviour,
+            turn_at_ledges: saved.turn_at_ledges,
+            patrol_left: saved.patrol_left,
+            patrol_right: saved.patrol_right,
+            health: saved.health,
+        }
+    }
+}
+
+fn deserialize_some<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+fn default_enemy_health() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MovingPlatformData {
+    /// where it starts
+    pub platform: BoxData,
+    /// offsets from the start it travels through in order, before heading back
+    pub waypoints: Vec<Vec2>,
+    /// units per second
+    pub speed: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HazardKind {
+    Spikes,
+    KillZone,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HazardData {
+    pub area: BoxData,
+    pub kind: HazardKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SlopeShape {
+    /// rises to the right
+    RampUp,
+    /// falls to the right
+    RampDown,
+    /// rounded bump, flat at both ends
+    Hill,
+}
+
+/// Sloped ground filling the bottom of a box, the shape decides its top
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SlopeData {
+    pub area: BoxData,
+    pub shape: SlopeShape,
+}
+
+/// Death plane for levels that don't set their own
+pub const DEFAULT_DEATH_PLANE: f32 = -25.;
+
+fn default_death_plane() -> f32 {
+    DEFAULT_DEATH_PLANE
+}
+
+/// Everything needed to spawn a level, as read from a `.level.ron` file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TypeUuid)]
+#[uuid = "5b0c7f6e-3a8d-4f61-9c2e-8e4b1d7a2f90"]
+pub struct LevelData {
+    pub player_spawn: Vec2,
+    pub boxes: Vec<BoxData>,
+    /// boxes that are only solid from above
+    #[serde(default)]
+    pub platforms: Vec<BoxData>,
+    /// boxes that travel back and forth along a path
+    #[serde(default)]
+    pub moving_platforms: Vec<MovingPlatformData>,
+    /// boxes that give way shortly after being landed on
+    #[serde(default)]
+    pub crumbling_platforms: Vec<BoxData>,
+    /// ground that can be walked up and down
+    #[serde(default)]
+    pub slopes: Vec<SlopeData>,
+    /// areas that kill anything touching them
+    #[serde(default)]
+    pub hazards: Vec<HazardData>,
+    pub enemies: Vec<EnemyData>,
+    /// where Mr Fuqheed waits, if he's in this level
+    #[serde(default)]
+    pub boss: Option<Vec2>,
+    /// ending cutscene sensor
+    pub trigger: BoxData,
+    /// respawn points
+    #[serde(default)]
+    pub checkpoints: Vec<Vec2>,
+    pub enemy_count: usize,
+    /// actors that fall below this are dead
+    #[serde(default = "default_death_plane")]
+    pub death_plane: f32,
+}
+
+/// One handle per entry in `LEVEL_PATHS`, in the same order
+#[derive(Resource)]
+pub struct LevelHandles(pub Vec<Handle<LevelData>>);
+
+#[derive(Default)]
+pub struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let level = ron::de::from_bytes::<LevelData>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(level));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+pub struct LevelLoaderPlugin;
+
+impl Plugin for LevelLoaderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<LevelData>()
+            .init_asset_loader::<LevelLoader>()
+            .add_startup_system(load_levels);
+    }
+}
+
+fn load_levels(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(LevelHandles(
+        LEVEL_PATHS
+            .iter()
+            .map(|path| asset_server.load(*path))
+            .collect(),
+    ));
+}
+
+/// Read a level straight from disk, bypassing the asset server
+pub fn read_level_file(path: impl AsRef<Path>) -> Result<LevelData, Box<dyn Error>> {
+    let bytes = fs::read(path)?;
+    Ok(ron::de::from_bytes(&bytes)?)
+}
+
+pub fn write_level_file(path: impl AsRef<Path>, level: &LevelData) -> Result<(), Box<dyn Error>> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(
+        path,
+        ron::ser::to_string_pretty(level, PrettyConfig::new())?,
+    )?;
+    Ok(())
+}
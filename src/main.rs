@@ -1,22 +1,32 @@
 mod background;
+mod boss;
 mod cutscene;
+mod editor_history;
 mod end_screen;
 mod enemy;
 mod genocide_ending;
+mod hazards;
 mod interfaces;
 mod intro_cutscene;
+mod karma;
 mod kinematic_physics;
 mod level;
 mod level_editor;
+mod level_loader;
+mod movement_config;
 mod normal_ending;
 mod pacifist_ending;
 mod pause;
+mod platforms;
 mod player;
+mod projectiles;
+mod slopes;
 mod states;
 mod util;
 
 use background::BackgroundPlugin;
 use bevy::audio::AudioSink;
+#[cfg(not(feature = "hot_reload"))]
 use bevy_embedded_assets::EmbeddedAssetPlugin;
 // use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::prelude::*;
@@ -29,25 +39,32 @@ use bevy_rapier2d::prelude::*;
 use bevy_parallax::ParallaxCameraComponent;
 
 use bevy_tweening::TweeningPlugin;
+use boss::BossPlugin;
 use cutscene::CutscenePlugin;
 use end_screen::EndScreenPlugin;
+use enemy::EnemyPlugin;
 use genocide_ending::GenocideEndingPlugin;
+use hazards::HazardsPlugin;
 use interfaces::{AudioVolume, UserInterfacesPlugin};
 use intro_cutscene::IntroCutscenePlugin;
+use karma::KarmaPlugin;
 use kinematic_physics::KinematicPhysics;
 use level::LevelPlugin;
 use level_editor::LevelEditorPlugin;
+use level_loader::LevelLoaderPlugin;
+use movement_config::MovementConfigPlugin;
 use normal_ending::NormalEndingPlugin;
 use pacifist_ending::PacifistEndingPlugin;
 use pause::PausePlugin;
+use platforms::PlatformsPlugin;
 use player::PlayerPlugin;
+use projectiles::ProjectilesPlugin;
+use slopes::SlopesPlugin;
 use states::StatesPlugin;
 use util::despawn_with;
 
 pub const CAMERA_SCALE: f32 = 1. / 24.;
 
-pub const DEATHPLANE: f32 = -25.;
-
 #[derive(Component)]
 pub struct Actor;
 
@@ -75,6 +92,7 @@ pub struct TextureHandles {
 #[derive(Resource)]
 pub struct SoundCollection {
     jump: Handle<AudioSource>,
+    wall_jump: Handle<AudioSource>,
     kill: Handle<AudioSource>,
     die: Handle<AudioSource>,
     fall: Handle<AudioSource>,
@@ -93,15 +111,22 @@ pub enum SystemOrderLabel {
     Input,
     Collisions,
     Movement,
+    Interpolation,
 }
 
 fn main() {
+    #[cfg(not(feature = "hot_reload"))]
+    let default_plugins = DefaultPlugins
+        .build()
+        .add_before::<bevy::asset::AssetPlugin, _>(EmbeddedAssetPlugin);
+    #[cfg(feature = "hot_reload")]
+    let default_plugins = DefaultPlugins.set(AssetPlugin {
+        watch_for_changes: true,
+        ..default()
+    });
+
     App::new()
-        .add_plugins(
-            DefaultPlugins
-                .build()
-                .add_before::<bevy::asset::AssetPlugin, _>(EmbeddedAssetPlugin),
-        )
+        .add_plugins(default_plugins)
         // .add_plugin(LogDiagnosticsPlugin::default())
         // .add_plugin(FrameTimeDiagnosticsPlugin::default())
         // setup
@@ -117,10 +142,20 @@ fn main() {
         .add_plugin(BackgroundPlugin)
         .add_plugin(StatesPlugin)
         .add_plugin(UserInterfacesPlugin)
+        // sets up the physics timestep the player and level systems run on
+        .add_plugin(KinematicPhysics)
         .add_plugin(PlayerPlugin)
         .add_plugin(PausePlugin)
+        .add_plugin(LevelLoaderPlugin)
+        .add_plugin(MovementConfigPlugin)
         .add_plugin(LevelPlugin)
-        .add_plugin(KinematicPhysics)
+        .add_plugin(KarmaPlugin)
+        .add_plugin(PlatformsPlugin)
+        .add_plugin(HazardsPlugin)
+        .add_plugin(SlopesPlugin)
+        .add_plugin(EnemyPlugin)
+        .add_plugin(ProjectilesPlugin)
+        .add_plugin(BossPlugin)
         .add_plugin(LevelEditorPlugin)
         .add_plugin(CutscenePlugin)
         .add_plugin(IntroCutscenePlugin)
@@ -133,7 +168,11 @@ fn main() {
         // particles
         .add_plugin(ParticleSystemPlugin)
         // physics
-        .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(1.0))
+        // stepped on the fixed timestep by KinematicPhysics
+        .add_plugin(
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(1.0)
+                .with_default_system_setup(false),
+        )
         // .add_plugin(RapierDebugRenderPlugin::default())
         .run();
 }
@@ -177,6 +216,7 @@ fn load_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
 
     commands.insert_resource(SoundCollection {
         jump: asset_server.load("sounds/jump.ogg"),
+        wall_jump: asset_server.load("sounds/wall_jump.ogg"),
         kill: asset_server.load("sounds/kill.ogg"),
         die: asset_server.load("sounds/die.ogg"),
         fall: asset_server.load("sounds/fall.ogg"),
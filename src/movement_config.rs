@@ -0,0 +1,159 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+/// Movement presets, the first is used on startup. F1 cycles through them
+pub const MOVEMENT_PRESET_PATHS: [&str; 3] = [
+    "movement/default.movement.ron",
+    "movement/floaty.movement.ron",
+    "movement/tight.movement.ron",
+];
+
+/// Everything that decides how movement feels, as read from a `.movement.ron`
+/// file. Speeds and accelerations are per physics tick, times are in seconds
+#[derive(Resource, Debug, Clone, PartialEq, Deserialize, TypeUuid)]
+#[uuid = "0d8b6a1e-72c4-4b5f-a3e9-6f1c2d9e8b47"]
+pub struct MovementConfig {
+    pub gravity: f32,
+    /// horizontal velocity is divided by this every tick
+    pub friction: f32,
+
+    pub player_walk_accel: f32,
+    pub player_walk_speed: f32,
+    pub player_jump_speed: f32,
+    /// upward speed is multiplied by this when jump is let go early
+    pub player_jump_cut: f32,
+    /// how long a jump press is remembered before landing
+    pub player_jump_buffer_time: f32,
+    /// grace period for jumping after walking off a ledge
+    pub player_coyote_time: f32,
+    /// fastest fall while pushing into a wall
+    pub player_wall_slide_speed: f32,
+    /// speed away from and up off the wall
+    pub player_wall_jump_speed: Vec2,
+    /// how long walking is ignored after a wall jump, so it can't undo the push
+    pub player_wall_jump_lock_time: f32,
+
+    pub enemy_walk_accel: f32,
+    pub enemy_walk_speed: f32,
+    /// top speed of a chaser that can see the player
+    pub enemy_chase_speed: f32,
+    pub enemy_jump_speed: f32,
+    /// time between a jumper's hops
+    pub enemy_jump_interval: f32,
+    pub enemy_shot_speed: f32,
+    /// time between a shooter's shots
+    pub enemy_shot_interval: f32,
+}
+
+// used until the preset has loaded, matches default.movement.ron
+impl Default for MovementConfig {
+    fn default() -> Self {
+        Self {
+            gravity: 0.1,
+            friction: 1.2,
+
+            player_walk_accel: 0.05,
+            player_walk_speed: 0.3,
+            player_jump_speed: 1.64,
+            player_jump_cut: 0.5,
+            player_jump_buffer_time: 0.1,
+            player_coyote_time: 0.1,
+            player_wall_slide_speed: 0.1,
+            player_wall_jump_speed: Vec2::new(0.3, 1.3),
+            player_wall_jump_lock_time: 0.15,
+
+            enemy_walk_accel: 0.05,
+            enemy_walk_speed: 0.05,
+            enemy_chase_speed: 0.15,
+            enemy_jump_speed: 1.,
+            enemy_jump_interval: 1.5,
+            enemy_shot_speed: 0.25,
+            enemy_shot_interval: 2.,
+        }
+    }
+}
+
+/// One handle per entry in `MOVEMENT_PRESET_PATHS`, in the same order
+#[derive(Resource)]
+pub struct MovementPresetHandles(pub Vec<Handle<MovementConfig>>);
+
+/// Index of the preset copied into `MovementConfig`
+#[derive(Resource, Default)]
+pub struct MovementPreset(pub usize);
+
+#[derive(Default)]
+pub struct MovementConfigLoader;
+
+impl AssetLoader for MovementConfigLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let config = ron::de::from_bytes::<MovementConfig>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(config));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["movement.ron"]
+    }
+}
+
+pub struct MovementConfigPlugin;
+
+impl Plugin for MovementConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<MovementConfig>()
+            .init_asset_loader::<MovementConfigLoader>()
+            .init_resource::<MovementConfig>()
+            .init_resource::<MovementPreset>()
+            .add_startup_system(load_movement_presets)
+            .add_system(cycle_movement_preset)
+            .add_system(apply_movement_config.after(cycle_movement_preset));
+    }
+}
+
+fn load_movement_presets(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(MovementPresetHandles(
+        MOVEMENT_PRESET_PATHS
+            .iter()
+            .map(|path| asset_server.load(*path))
+            .collect(),
+    ));
+}
+
+fn cycle_movement_preset(input: Res<Input<KeyCode>>, mut preset: ResMut<MovementPreset>) {
+    if input.just_pressed(KeyCode::F1) {
+        preset.0 = (preset.0 + 1) % MOVEMENT_PRESET_PATHS.len();
+        info!("movement preset: {}", MOVEMENT_PRESET_PATHS[preset.0]);
+    }
+}
+
+// copies the preset in when it's picked, finishes loading or is edited on disk
+fn apply_movement_config(
+    mut asset_events: EventReader<AssetEvent<MovementConfig>>,
+    configs: Res<Assets<MovementConfig>>,
+    handles: Res<MovementPresetHandles>,
+    preset: Res<MovementPreset>,
+    mut config: ResMut<MovementConfig>,
+) {
+    let handle = &handles.0[preset.0];
+    let changed = asset_events.iter().any(|event| match event {
+        AssetEvent::Created { handle: h } | AssetEvent::Modified { handle: h } => h == handle,
+        AssetEvent::Removed { .. } => false,
+    });
+
+    if changed || preset.is_changed() {
+        if let Some(loaded) = configs.get(handle) {
+            *config = loaded.clone();
+        }
+    }
+}
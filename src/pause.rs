@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::RapierConfiguration;
 use iyes_loopless::{
-    prelude::{AppLooplessStateExt, IntoConditionalSystem},
+    prelude::{AppLooplessStateExt, ConditionHelpers, IntoConditionalSystem},
     state::{CurrentState, NextState},
 };
 
@@ -15,7 +15,11 @@ impl Plugin for PausePlugin {
             .add_enter_system(PauseState::Paused, pause_physics)
             .add_exit_system(PauseState::Paused, unpause_physics)
             // pause systems
-            .add_system(pause_input.run_not_in_state(GameState::MainMenu));
+            .add_system(
+                pause_input
+                    .run_not_in_state(GameState::MainMenu)
+                    .run_not_in_state(GameState::LevelSelect),
+            );
     }
 }
 
@@ -0,0 +1,301 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::{
+    fixedtimestep::{app::AppLooplessFixedTimestepExt, FixedTimesteps},
+    prelude::{ConditionHelpers, IntoConditionalSystem},
+};
+
+use crate::{
+    kinematic_physics::{
+        physics_dt, CCVelocity, PhysicsInterpolation, MOVEMENT_SUBSTAGE, PHYSICS_TIMESTEP,
+    },
+    level::create_box,
+    level_loader::{BoxData, MovingPlatformData},
+    player::Player,
+    states::{GameState, PauseState},
+    SystemOrderLabel, TextureHandles,
+};
+
+// how long a crumbling platform holds after being landed on, and how long it's gone for
+const CRUMBLE_TIME: f32 = 0.5;
+const CRUMBLE_RESPAWN_TIME: f32 = 3.;
+const CRUMBLE_BLINK_TIME: f32 = 0.1;
+
+// furthest below a character something still counts as being stood on. platforms
+// can move a tick's worth away before whoever's on them follows
+const STANDING_DISTANCE: f32 = 0.25;
+
+/// A box that travels through its waypoints and back to the start, carrying
+/// anything standing on it
+#[derive(Component)]
+pub struct MovingPlatform {
+    /// offsets from `start`
+    pub waypoints: Vec<Vec2>,
+    /// units per second
+    pub speed: f32,
+    start: Vec2,
+    /// index into the path, where 0 is the start and the rest are the waypoints
+    next: usize,
+    /// how far it moved last tick
+    step: Vec2,
+}
+
+impl MovingPlatform {
+    fn path_point(&self, index: usize) -> Vec2 {
+        match index {
+            0 => self.start,
+            i => self.start + self.waypoints[i - 1],
+        }
+    }
+}
+
+/// A box that breaks a moment after the player lands on it, and comes back later
+#[derive(Component, Default)]
+pub struct CrumblingPlatform {
+    state: CrumbleState,
+}
+
+#[derive(Default)]
+enum CrumbleState {
+    #[default]
+    Solid,
+    Crumbling(Timer),
+    Broken(Timer),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemLabel)]
+enum PlatformLabel {
+    Carry,
+}
+
+pub struct PlatformsPlugin;
+
+impl Plugin for PlatformsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_timestep_system(
+            PHYSICS_TIMESTEP,
+            MOVEMENT_SUBSTAGE,
+            carry_on_platforms
+                .run_in_state(GameState::InGame)
+                .run_in_state(PauseState::Running)
+                .label(PlatformLabel::Carry)
+                .after(SystemOrderLabel::Movement),
+        )
+        .add_fixed_timestep_system(
+            PHYSICS_TIMESTEP,
+            MOVEMENT_SUBSTAGE,
+            move_platforms
+                .run_in_state(GameState::InGame)
+                .run_in_state(PauseState::Running)
+                .after(PlatformLabel::Carry),
+        )
+        .add_fixed_timestep_system(
+            PHYSICS_TIMESTEP,
+            MOVEMENT_SUBSTAGE,
+            crumble_platforms
+                .run_in_state(GameState::InGame)
+                .run_in_state(PauseState::Running)
+                .after(SystemOrderLabel::Movement),
+        );
+    }
+}
+
+pub fn create_moving_platform(
+    commands: &mut Commands,
+    platform: &MovingPlatformData,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) -> Entity {
+    let BoxData { tl, br } = platform.platform;
+    let e = create_box(commands, tl, br, texture_handles, meshes, materials);
+
+    commands
+        .entity(e)
+        .insert(MovingPlatform {
+            waypoints: platform.waypoints.clone(),
+            speed: platform.speed,
+            start: (tl + br) / 2.,
+            next: 1 % (platform.waypoints.len() + 1),
+            step: Vec2::ZERO,
+        })
+        .insert(PhysicsInterpolation::default());
+    e
+}
+
+pub fn create_crumbling_platform(
+    commands: &mut Commands,
+    platform: BoxData,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) -> Entity {
+    let e = create_box(
+        commands,
+        platform.tl,
+        platform.br,
+        texture_handles,
+        meshes,
+        materials,
+    );
+    commands.entity(e).insert(CrumblingPlatform::default());
+    e
+}
+
+/// The first collider `predicate` accepts just below a character
+fn standing_on(
+    rapier_context: &RapierContext,
+    character: Entity,
+    transform: &Transform,
+    collider: &Collider,
+    predicate: impl Fn(Entity) -> bool,
+) -> Option<Entity> {
+    rapier_context
+        .cast_shape(
+            transform.translation.truncate(),
+            0.,
+            Vec2::NEG_Y,
+            collider,
+            STANDING_DISTANCE,
+            QueryFilter::default()
+                .exclude_sensors()
+                .exclude_collider(character)
+                .predicate(&predicate),
+        )
+        .map(|(entity, _)| entity)
+}
+
+// characters move against where platforms were after the last tick, so they're
+// carried by last tick's step to end up back on top
+fn carry_on_platforms(
+    rapier_context: Res<RapierContext>,
+    mut q_characters: Query<(
+        Entity,
+        &Transform,
+        &Collider,
+        &CCVelocity,
+        &mut KinematicCharacterController,
+    )>,
+    q_platforms: Query<&MovingPlatform>,
+) {
+    for (entity, transform, collider, vel, mut controller) in &mut q_characters {
+        // jumping off
+        if vel.0.y > 0. {
+            continue;
+        }
+
+        let Some(platform) = standing_on(&rapier_context, entity, transform, collider, |e| {
+            q_platforms.contains(e)
+        })
+        .and_then(|e| q_platforms.get(e).ok()) else {
+            continue;
+        };
+
+        controller.translation = Some(controller.translation.unwrap_or_default() + platform.step);
+    }
+}
+
+fn move_platforms(
+    timesteps: Res<FixedTimesteps>,
+    mut q_platforms: Query<(&mut Transform, &mut MovingPlatform)>,
+) {
+    let dt = physics_dt(&timesteps);
+
+    for (mut transform, mut platform) in &mut q_platforms {
+        let position = transform.translation.truncate();
+        let target = platform.path_point(platform.next);
+        let travel = platform.speed * dt;
+
+        let new_position = if position.distance(target) <= travel {
+            platform.next = (platform.next + 1) % (platform.waypoints.len() + 1);
+            target
+        } else {
+            position + (target - position).normalize() * travel
+        };
+
+        platform.step = new_position - position;
+        transform.translation.x = new_position.x;
+        transform.translation.y = new_position.y;
+    }
+}
+
+fn crumble_platforms(
+    mut commands: Commands,
+    timesteps: Res<FixedTimesteps>,
+    rapier_context: Res<RapierContext>,
+    q_player: Query<
+        (
+            Entity,
+            &Transform,
+            &Collider,
+            &KinematicCharacterControllerOutput,
+        ),
+        With<Player>,
+    >,
+    q_characters: Query<(), With<KinematicCharacterController>>,
+    mut q_platforms: Query<(
+        Entity,
+        &Transform,
+        &Collider,
+        &mut CrumblingPlatform,
+        &mut Visibility,
+    )>,
+) {
+    let dt = std::time::Duration::from_secs_f32(physics_dt(&timesteps));
+
+    let landed_on = q_player
+        .iter()
+        .filter(|(.., output)| output.grounded)
+        .find_map(|(player, transform, collider, _)| {
+            standing_on(&rapier_context, player, transform, collider, |e| {
+                q_platforms.contains(e)
+            })
+        });
+
+    for (entity, transform, collider, mut platform, mut visibility) in &mut q_platforms {
+        match &mut platform.state {
+            CrumbleState::Solid => {
+                if landed_on == Some(entity) {
+                    platform.state =
+                        CrumbleState::Crumbling(Timer::from_seconds(CRUMBLE_TIME, TimerMode::Once));
+                }
+            }
+            CrumbleState::Crumbling(timer) => {
+                timer.tick(dt);
+                // blink as a warning
+                let blinks = (timer.elapsed_secs() / CRUMBLE_BLINK_TIME) as u32;
+                visibility.is_visible = blinks.is_multiple_of(2);
+
+                if timer.finished() {
+                    commands.entity(entity).insert(ColliderDisabled);
+                    visibility.is_visible = false;
+                    platform.state = CrumbleState::Broken(Timer::from_seconds(
+                        CRUMBLE_RESPAWN_TIME,
+                        TimerMode::Once,
+                    ));
+                }
+            }
+            CrumbleState::Broken(timer) => {
+                timer.tick(dt);
+
+                // don't come back around anyone
+                let occupied = rapier_context
+                    .intersection_with_shape(
+                        transform.translation.truncate(),
+                        0.,
+                        collider,
+                        QueryFilter::default()
+                            .exclude_sensors()
+                            .predicate(&|e| q_characters.contains(e)),
+                    )
+                    .is_some();
+
+                if timer.finished() && !occupied {
+                    commands.entity(entity).remove::<ColliderDisabled>();
+                    visibility.is_visible = true;
+                    platform.state = CrumbleState::Solid;
+                }
+            }
+        }
+    }
+}
@@ -1,112 +1,384 @@
-use std::time::Duration;
-
-use bevy::prelude::*;
+use bevy::{input::InputSystem, prelude::*, transform::TransformSystem};
 use bevy_rapier2d::prelude::*;
 use iyes_loopless::{
+    fixedtimestep::{app::AppLooplessFixedTimestepExt, FixedTimesteps},
     prelude::{ConditionHelpers, IntoConditionalSystem},
     state::NextState,
 };
 
 use crate::{
-    end_screen::{Ending, Endings},
+    boss::{Boss, BossResult},
+    end_screen::Ending,
     enemy::Enemy,
-    kinematic_physics::{CCAcceleration, CCVelocity, KinematicGravity},
-    level::{LevelEnemyCount, Trigger},
+    interfaces::AudioVolume,
+    karma::{choose_ending, Karma, KarmaEvent},
+    kinematic_physics::{
+        character_controller, physics_dt, CCAcceleration, CCVelocity, KinematicGravity,
+        PhysicsInterpolation, MOVEMENT_SUBSTAGE, PHYSICS_TIMESTEP,
+    },
+    level::{
+        Checkpoint, CurrentLevel, LevelEnemy, LevelEnemyCount, ReachedCheckpoint, Trigger,
+        CHECKPOINT_COLOR, CHECKPOINT_REACHED_COLOR,
+    },
+    level_editor::Playtest,
+    movement_config::MovementConfig,
     states::{GameState, PauseState},
-    Actor, CameraScale, InGameItem, SoundCollection, SystemOrderLabel, TextureHandles, interfaces::AudioVolume,
+    Actor, ActorDead, CameraScale, InGameItem, SoundCollection, SystemOrderLabel, TextureHandles,
 };
 
 #[derive(Component)]
 pub struct Player {
-    jump_start: f32,
-    pub can_jump: Timer,
+    /// physics time since last standing on the ground, for coyote time
+    air_time: f32,
+    /// physics time since jump was last pressed, so early presses still count
+    jump_buffer: f32,
+    /// rising from a jump that letting go of jump can still cut short
+    jumping: bool,
+    /// physics time since the last wall jump
+    since_wall_jump: f32,
+    /// holding down to fall through one-way platforms
+    pub dropping: bool,
+}
+
+/// Walls beside the player, found by `player_wall_raycast` every tick
+#[derive(Component, Default)]
+pub struct WallContact {
+    /// x of the wall's normal, so which way to push off it
+    pub normal: Option<f32>,
+    /// walking into the wall, so sliding down it
+    pub pressing: bool,
+}
+
+pub const PLAYER_MAX_HEALTH: u32 = 3;
+pub const PLAYER_LIVES: u32 = 3;
+
+// how long the player can't be hurt again after a hit, blinking the whole time
+pub const PLAYER_INVULNERABLE_TIME: f32 = 1.5;
+const PLAYER_BLINK_TIME: f32 = 0.1;
+
+// velocity the player is knocked away from whatever hit them with
+const PLAYER_KNOCKBACK: Vec2 = Vec2::new(0.3, 0.8);
+
+/// Hits the player can take before dying, and how long until another one counts
+#[derive(Component)]
+pub struct PlayerHealth {
+    pub health: u32,
+    pub invulnerable: f32,
+}
+
+/// Hit by something at `from` that hurts rather than kills outright. Inserted
+/// instead of `ActorDead`, `player_take_hit` decides what it costs
+#[derive(Component)]
+pub struct PlayerHurt {
+    pub from: Vec2,
+}
+
+/// Sent when the player is despawned for dying, rather than swapped out for
+/// the next level
+pub struct PlayerDied;
+
+/// Deaths left this run before it's game over
+#[derive(Resource)]
+pub struct Lives(pub u32);
+
+impl Default for Lives {
+    fn default() -> Self {
+        Self(PLAYER_LIVES)
+    }
+}
+
+/// Keys sampled every frame for the physics timestep to use. A jump press is
+/// held onto until a tick uses it, so it isn't lost on frames without a tick
+#[derive(Resource, Default)]
+struct PlayerInput {
+    left: bool,
+    right: bool,
+    down: bool,
+    jump_held: bool,
+    jump_pressed: bool,
 }
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(
-            camera_follow_player
-                .run_in_state(GameState::InGame)
-                .run_in_state(PauseState::Running),
-        )
-        .add_system(
-            player_movement
-                .run_in_state(GameState::InGame)
-                .run_in_state(PauseState::Running)
-                .label(SystemOrderLabel::Input),
-        )
-        .add_system(
-            detect_triggers
-                .run_in_state(GameState::InGame)
-                .run_in_state(PauseState::Running)
-                .label(SystemOrderLabel::Collisions),
-        )
-        .add_system(
-            detect_player_removed
-                .run_in_state(GameState::InGame)
-                .run_in_state(PauseState::Running),
-        );
+        app.init_resource::<PlayerInput>()
+            .init_resource::<Lives>()
+            .add_event::<PlayerDied>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                camera_follow_player
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(PauseState::Running)
+                    .after(SystemOrderLabel::Interpolation)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                sample_player_input
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(PauseState::Running)
+                    .after(InputSystem),
+            )
+            // same stage as the `ActorDead` despawn, so the player is still there
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                send_player_died.run_in_state(GameState::InGame),
+            )
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                MOVEMENT_SUBSTAGE,
+                player_movement
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(PauseState::Running)
+                    .label(SystemOrderLabel::Input),
+            )
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                MOVEMENT_SUBSTAGE,
+                player_take_hit
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(PauseState::Running)
+                    .label(SystemOrderLabel::Input),
+            )
+            .add_system(update_player_looks.run_in_state(GameState::InGame))
+            .add_system(
+                detect_triggers
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(PauseState::Running)
+                    .label(SystemOrderLabel::Collisions),
+            )
+            .add_system(
+                detect_checkpoints
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(PauseState::Running)
+                    .label(SystemOrderLabel::Collisions),
+            )
+            .add_system(
+                detect_player_died
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(PauseState::Running),
+            );
+    }
+}
+
+fn send_player_died(
+    q_dead: Query<(), (With<Player>, With<ActorDead>)>,
+    mut ev_died: EventWriter<PlayerDied>,
+) {
+    if !q_dead.is_empty() {
+        ev_died.send(PlayerDied);
     }
 }
 
-fn detect_player_removed(
+#[allow(clippy::too_many_arguments)]
+fn detect_player_died(
     mut commands: Commands,
-    removals: RemovedComponents<Player>,
+    mut ev_died: EventReader<PlayerDied>,
     audio: Res<Audio>,
     sound_collection: Res<SoundCollection>,
     audio_volume: Res<AudioVolume>,
+    playtest: Option<Res<Playtest>>,
+    mut lives: ResMut<Lives>,
+    mut karma: ResMut<Karma>,
 ) {
-    for _entity in removals.iter() {
+    for _ in ev_died.iter() {
         audio.play_with_settings(sound_collection.die.clone(), PlaybackSettings::ONCE.with_volume(audio_volume.0));
 
-        commands.insert_resource(NextState(GameState::Dead));
+        if playtest.is_some() {
+            commands.insert_resource(NextState(GameState::LevelEditor));
+            continue;
+        }
+
+        karma.record(KarmaEvent::Death);
+        if lives.0 > 1 {
+            // straight back in at the last checkpoint
+            lives.0 -= 1;
+            commands.insert_resource(NextState(GameState::InGame));
+        } else {
+            // game over, replaying starts the level again from the top
+            *lives = Lives::default();
+            commands.remove_resource::<ReachedCheckpoint>();
+            commands.insert_resource(NextState(GameState::Dead));
+        }
     }
 }
 
+#[allow(clippy::type_complexity)]
+fn player_take_hit(
+    mut commands: Commands,
+    timesteps: Res<FixedTimesteps>,
+    mut q_player: Query<
+        (
+            Entity,
+            &Transform,
+            &mut CCVelocity,
+            &mut PlayerHealth,
+            Option<&PlayerHurt>,
+        ),
+        Without<ActorDead>,
+    >,
+    audio: Res<Audio>,
+    sound_collection: Res<SoundCollection>,
+    audio_volume: Res<AudioVolume>,
+) {
+    let dt = physics_dt(&timesteps);
+
+    for (player, transform, mut vel, mut health, hurt) in &mut q_player {
+        if health.invulnerable > 0. {
+            health.invulnerable = (health.invulnerable - dt).max(0.);
+        }
+
+        let Some(hurt) = hurt else {
+            continue;
+        };
+        commands.entity(player).remove::<PlayerHurt>();
+
+        // still blinking from the last one
+        if health.invulnerable > 0. {
+            continue;
+        }
+
+        health.health = health.health.saturating_sub(1);
+        if health.health == 0 {
+            commands.entity(player).insert(ActorDead);
+            continue;
+        }
+
+        audio.play_with_settings(
+            sound_collection.angry.clone(),
+            PlaybackSettings::ONCE.with_volume(audio_volume.0),
+        );
+
+        let away = (transform.translation.x - hurt.from.x).signum();
+        vel.0 = Vec2::new(away * PLAYER_KNOCKBACK.x, PLAYER_KNOCKBACK.y);
+        health.invulnerable = PLAYER_INVULNERABLE_TIME;
+    }
+}
+
+fn update_player_looks(mut q_player: Query<(&PlayerHealth, &mut Visibility), With<Player>>) {
+    for (health, mut visibility) in &mut q_player {
+        let blinks = (health.invulnerable / PLAYER_BLINK_TIME) as u32;
+        let visible = health.invulnerable == 0. || blinks.is_multiple_of(2);
+
+        if visibility.is_visible != visible {
+            visibility.is_visible = visible;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn detect_triggers(
     rapier_context: Res<RapierContext>,
     q_player: Query<Entity, With<Player>>,
-    q_triggers: Query<Entity, With<Trigger>>,
+    q_triggers: Query<(Entity, &Trigger)>,
     mut commands: Commands,
     level_enemy_count: Res<LevelEnemyCount>,
     q_enemies: Query<&Enemy>,
+    q_bosses: Query<(), With<Boss>>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut karma: ResMut<Karma>,
+    mut boss_result: ResMut<BossResult>,
 
     audio: Res<Audio>,
     sound_collection: Res<SoundCollection>,
     audio_volume: Res<AudioVolume>,
+    playtest: Option<Res<Playtest>>,
 ) {
     if let Ok(player) = q_player.get_single() {
-        for trigger in q_triggers.iter() {
+        for (trigger, kind) in q_triggers.iter() {
             if rapier_context.intersection_pair(player, trigger) == Some(true) {
                 let alive_enemies = q_enemies.iter().count();
 
                 audio.play_with_settings(sound_collection.land.clone(), PlaybackSettings::ONCE.with_volume(audio_volume.0));
 
-                if alive_enemies == 0 {
-                    // genocide
-                    commands.insert_resource(Ending(Endings::Genocide));
-                    commands.insert_resource(NextState(GameState::GenocideEnding));
-                } else if alive_enemies == level_enemy_count.0 {
-                    // pacifist
-                    commands.insert_resource(Ending(Endings::Pacifist));
-                    commands.insert_resource(NextState(GameState::PacifistEnding));
+                // no cutscenes when playtesting, straight back to the editor
+                if playtest.is_some() {
+                    commands.insert_resource(NextState(GameState::LevelEditor));
+                    return;
+                }
+
+                let killed = level_enemy_count.0.saturating_sub(alive_enemies);
+                for _ in 0..killed {
+                    karma.record(KarmaEvent::Kill);
+                }
+                for _ in killed..level_enemy_count.0 {
+                    karma.record(KarmaEvent::Spare);
+                }
+
+                // made it past mr fuqheed without finishing him off
+                if !q_bosses.is_empty() {
+                    *boss_result = BossResult::Spared;
+                    karma.record(KarmaEvent::BossSpared);
+                } else if *boss_result == BossResult::Defeated {
+                    karma.record(KarmaEvent::BossDefeated);
+                }
+
+                if *kind == Trigger::NextLevel {
+                    current_level.0 += 1;
+                    // checkpoints only mean anything in their own level
+                    commands.remove_resource::<ReachedCheckpoint>();
+                    commands.insert_resource(NextState(GameState::InGame));
                 } else {
-                    commands.insert_resource(Ending(Endings::Normal));
-                    commands.insert_resource(NextState(GameState::NormalEnding));
+                    let rule = choose_ending(&karma);
+                    commands.insert_resource(Ending(rule.ending));
+                    commands.insert_resource(NextState(rule.state));
                 }
+
+                // only count the level once
+                return;
             }
         }
     }
 }
 
-const PLAYER_JUMP_ACCEL: f32 = 0.4;
-const PLAYER_JUMP_MAX_DURATION: f32 = 1.;
-const PLAYER_JUMP_FALLOFF_EXPONENT: f32 = 12.;
-const PLAYER_WALK_ACCEL: f32 = 0.05;
+fn detect_checkpoints(
+    mut commands: Commands,
+    rapier_context: Res<RapierContext>,
+    q_player: Query<Entity, With<Player>>,
+    mut q_checkpoints: Query<(Entity, &Checkpoint, &Transform, &mut Sprite)>,
+    q_enemies: Query<&LevelEnemy>,
+    reached: Option<Res<ReachedCheckpoint>>,
+    playtest: Option<Res<Playtest>>,
+) {
+    // playtests go back to the editor on death, nothing to respawn at
+    if playtest.is_some() {
+        return;
+    }
+
+    let Ok(player) = q_player.get_single() else {
+        return;
+    };
 
-const PLAYER_COYOTE_TIME: f32 = 0.05;
+    let reached_index = reached.map(|r| r.index);
+    let mut touched = None;
+
+    for (checkpoint, Checkpoint(index), transform, _) in &q_checkpoints {
+        if Some(*index) != reached_index
+            && rapier_context.intersection_pair(player, checkpoint) == Some(true)
+        {
+            touched = Some((*index, transform.translation.truncate()));
+        }
+    }
+
+    let Some((index, position)) = touched else {
+        return;
+    };
+
+    commands.insert_resource(ReachedCheckpoint {
+        index,
+        position,
+        alive_enemies: q_enemies.iter().map(|enemy| enemy.0).collect(),
+    });
+
+    for (_, checkpoint, _, mut sprite) in &mut q_checkpoints {
+        sprite.color = if checkpoint.0 == index {
+            CHECKPOINT_REACHED_COLOR
+        } else {
+            CHECKPOINT_COLOR
+        };
+    }
+}
 
 pub const PLAYER_RADIUS: f32 = 0.8;
 
@@ -121,18 +393,26 @@ pub fn spawn_player(commands: &mut Commands, texture_handles: &TextureHandles, p
         ))
         .insert(Collider::ball(PLAYER_RADIUS))
         .insert(KinematicCharacterController {
-            apply_impulse_to_dynamic_bodies: true,
             translation: Some(Vec2::ZERO),
             filter_groups: Some(CollisionGroups::new(Group::GROUP_2, Group::GROUP_2)),
             filter_flags: QueryFilterFlags::EXCLUDE_SENSORS,
-            ..default()
+            ..character_controller()
         })
         .insert(CCAcceleration(Vec2::new(0., 0.)))
         .insert(CCVelocity(Vec2::new(0., 0.)))
         .insert(KinematicGravity)
+        .insert(PhysicsInterpolation::default())
         .insert(Player {
-            jump_start: 0.,
-            can_jump: Timer::new(Duration::from_secs_f32(PLAYER_COYOTE_TIME), TimerMode::Once),
+            air_time: 0.,
+            jump_buffer: f32::INFINITY,
+            jumping: false,
+            since_wall_jump: f32::INFINITY,
+            dropping: false,
+        })
+        .insert(WallContact::default())
+        .insert(PlayerHealth {
+            health: PLAYER_MAX_HEALTH,
+            invulnerable: 0.,
         })
         .insert(InGameItem)
         .insert(Actor)
@@ -171,75 +451,98 @@ pub fn spawn_player(commands: &mut Commands, texture_handles: &TextureHandles, p
         });
 }
 
+fn sample_player_input(keyboard_input: Res<Input<KeyCode>>, mut player_input: ResMut<PlayerInput>) {
+    player_input.jump_held = keyboard_input.any_pressed([KeyCode::W, KeyCode::Up, KeyCode::Space]);
+    player_input.left = keyboard_input.any_pressed([KeyCode::A, KeyCode::Left]);
+    player_input.right = keyboard_input.any_pressed([KeyCode::D, KeyCode::Right]);
+    player_input.down = keyboard_input.any_pressed([KeyCode::S, KeyCode::Down]);
+
+    // cleared by the tick that uses it
+    if keyboard_input.any_just_pressed([KeyCode::W, KeyCode::Up, KeyCode::Space]) {
+        player_input.jump_pressed = true;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn player_movement(
-    time: Res<Time>,
-    keyboard_input: Res<Input<KeyCode>>,
+    timesteps: Res<FixedTimesteps>,
+    mut player_input: ResMut<PlayerInput>,
+    config: Res<MovementConfig>,
     mut player_info: Query<(
         &KinematicCharacterControllerOutput,
         &mut CCAcceleration,
         &mut CCVelocity,
         &mut Player,
+        &WallContact,
     )>,
 
+    mut karma: ResMut<Karma>,
+
     audio: Res<Audio>,
     sound_collection: Res<SoundCollection>,
     audio_volume: Res<AudioVolume>,
 ) {
-    for (output, mut acc, mut vel, mut player) in &mut player_info {
-        let up_start = keyboard_input.any_just_pressed([KeyCode::W, KeyCode::Up, KeyCode::Space]);
-        let up_held = keyboard_input.any_pressed([KeyCode::W, KeyCode::Up, KeyCode::Space]);
-        // let down = keyboard_input.any_pressed([KeyCode::S, KeyCode::Down]);
-        let left = keyboard_input.any_pressed([KeyCode::A, KeyCode::Left]);
-        let right = keyboard_input.any_pressed([KeyCode::D, KeyCode::Right]);
+    let dt = physics_dt(&timesteps);
+    let up_start = std::mem::take(&mut player_input.jump_pressed);
+    let up_held = player_input.jump_held;
+    let left = player_input.left;
+    let right = player_input.right;
+    let down = player_input.down;
 
+    for (output, mut acc, mut vel, mut player, wall) in &mut player_info {
         if output.grounded {
-            if player.can_jump.finished() {
+            if player.air_time > config.player_coyote_time {
                 audio.play_with_settings(sound_collection.land.clone(), PlaybackSettings::ONCE.with_volume(audio_volume.0));
-                player.can_jump.reset();
             }
+            player.air_time = 0.;
         } else {
-            player.can_jump.tick(time.delta());
+            player.air_time += dt;
+        }
+
+        player.jump_buffer = if up_start { 0. } else { player.jump_buffer + dt };
+        player.since_wall_jump += dt;
+        player.dropping = down;
+
+        // JUMP CUT
+        if player.jumping && (!up_held || vel.0.y <= 0.) {
+            if vel.0.y > 0. {
+                vel.0.y *= config.player_jump_cut;
+            }
+            player.jumping = false;
         }
 
-        let x_axis = (-(left as i8) + right as i8) as f32 * PLAYER_WALK_ACCEL;
+        let jump_buffered = player.jump_buffer <= config.player_jump_buffer_time;
 
-        let y_axis = if up_start && !player.can_jump.finished() {
-            // JUMP
+        // JUMP
+        if jump_buffered && player.air_time <= config.player_coyote_time {
             audio.play_with_settings(sound_collection.jump.clone(), PlaybackSettings::ONCE.with_volume(audio_volume.0));
 
-            player.jump_start = time.elapsed_seconds();
-            PLAYER_JUMP_ACCEL
-        } else if up_held && time.elapsed_seconds() - PLAYER_JUMP_MAX_DURATION < player.jump_start {
-            PLAYER_JUMP_ACCEL
-                * (1. - (time.elapsed_seconds() - player.jump_start) / PLAYER_JUMP_MAX_DURATION)
-                    .powf(PLAYER_JUMP_FALLOFF_EXPONENT)
-        } else {
-            0.
-        };
+            vel.0.y = config.player_jump_speed;
+            player.jumping = true;
+            karma.record(KarmaEvent::Jump);
+            // both used up, no jumping again until landing
+            player.jump_buffer = f32::INFINITY;
+            player.air_time = f32::INFINITY;
+        // WALL JUMP
+        } else if let (true, Some(normal)) = (jump_buffered, wall.normal) {
+            audio.play_with_settings(sound_collection.wall_jump.clone(), PlaybackSettings::ONCE.with_volume(audio_volume.0));
 
-        acc.0.x += x_axis;
-        acc.0.y = if y_axis != 0. {
-            // JUMP
-            // extra jump check superfluous but allows expansion elsewhere
-            vel.0.y = if vel.0.y < 0. && !player.can_jump.finished() {
-                0.
-            } else {
-                vel.0.y
-            };
-            player
-                .can_jump
-                .set_elapsed(Duration::from_secs_f32(PLAYER_COYOTE_TIME));
-
-            y_axis
-        } else {
-            0.
+            vel.0 = Vec2::new(normal * config.player_wall_jump_speed.x, config.player_wall_jump_speed.y);
+            player.jumping = true;
+            player.jump_buffer = f32::INFINITY;
+            player.since_wall_jump = 0.;
+            karma.record(KarmaEvent::Jump);
+        }
+
+        if player.since_wall_jump >= config.player_wall_jump_lock_time {
+            acc.0.x += (-(left as i8) + right as i8) as f32 * config.player_walk_accel;
         }
     }
 }
 
 fn camera_follow_player(
     mut camera_transform: Query<&mut Transform, With<Camera2d>>,
-    q_player: Query<&GlobalTransform, With<Player>>,
+    q_player: Query<&Transform, (With<Player>, Without<Camera2d>)>,
     windows: Res<Windows>,
     camera_scale: Res<CameraScale>,
 ) {
@@ -247,7 +550,7 @@ fn camera_follow_player(
     for player_transform in &q_player {
         let window = windows.get_primary().unwrap();
         camera_transform.single_mut().translation = Vec3::new(
-            player_transform.translation().x + (window.width() * 0.3 * camera_scale.0),
+            player_transform.translation.x + (window.width() * 0.3 * camera_scale.0),
             0.,
             // player_transform.translation().y + 25.,
             0.,
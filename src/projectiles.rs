@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::{
+    fixedtimestep::{app::AppLooplessFixedTimestepExt, FixedTimesteps},
+    prelude::{ConditionHelpers, IntoConditionalSystem},
+};
+
+use crate::{
+    enemy::{EnemyShooter, EnemyStunned, KillPlayerHitbox},
+    kinematic_physics::{
+        in_sight, physics_dt, PhysicsInterpolation, MOVEMENT_SUBSTAGE, PHYSICS_TIMESTEP,
+    },
+    level::{OneWayPlatform, Wall},
+    movement_config::MovementConfig,
+    player::{Player, PlayerHurt, PLAYER_RADIUS},
+    states::{GameState, PauseState},
+    Actor, ActorDead, InGameItem, SystemOrderLabel, TextureHandles,
+};
+
+const PROJECTILE_RADIUS: f32 = 0.4;
+
+// how long a projectile flies for if it doesn't hit anything
+const PROJECTILE_LIFETIME: f32 = 4.;
+
+// how far away shooters start firing at the player
+const SHOOT_RANGE: f32 = 15.;
+
+/// Flies in a straight line until it hits a wall, an actor or runs out of time
+#[derive(Component)]
+pub struct Projectile {
+    /// units per tick
+    pub velocity: Vec2,
+    lifetime: Timer,
+}
+
+pub struct ProjectilesPlugin;
+
+impl Plugin for ProjectilesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_timestep_system(
+            PHYSICS_TIMESTEP,
+            MOVEMENT_SUBSTAGE,
+            enemy_shoot
+                .run_in_state(GameState::InGame)
+                .run_in_state(PauseState::Running)
+                .after(SystemOrderLabel::Movement),
+        )
+        .add_fixed_timestep_system(
+            PHYSICS_TIMESTEP,
+            MOVEMENT_SUBSTAGE,
+            move_projectiles
+                .run_in_state(GameState::InGame)
+                .run_in_state(PauseState::Running)
+                .after(SystemOrderLabel::Movement),
+        )
+        .add_system(
+            projectile_hit_actor
+                .run_in_state(GameState::InGame)
+                .run_in_state(PauseState::Running),
+        );
+    }
+}
+
+/// A "ha" flying from `position` at `velocity` units per tick, that kills the
+/// player if it reaches them
+pub fn spawn_projectile(
+    commands: &mut Commands,
+    texture_handles: &TextureHandles,
+    position: Vec2,
+    velocity: Vec2,
+) -> Entity {
+    commands
+        .spawn(SpriteBundle {
+            texture: texture_handles.ha.clone().unwrap(),
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(PROJECTILE_RADIUS * 2.)),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(10.)),
+            ..default()
+        })
+        .insert(RigidBody::KinematicPositionBased)
+        .insert(Collider::ball(PROJECTILE_RADIUS))
+        .insert(Sensor)
+        // actors are kinematic too
+        .insert(ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_KINEMATIC)
+        .insert(KillPlayerHitbox)
+        .insert(Projectile {
+            velocity,
+            lifetime: Timer::from_seconds(PROJECTILE_LIFETIME, TimerMode::Once),
+        })
+        .insert(PhysicsInterpolation::default())
+        .insert(InGameItem)
+        .id()
+}
+
+fn enemy_shoot(
+    mut commands: Commands,
+    timesteps: Res<FixedTimesteps>,
+    rapier_context: Res<RapierContext>,
+    q_player: Query<(Entity, &Transform), With<Player>>,
+    mut q_shooters: Query<(Entity, &Transform, &mut EnemyShooter), Without<EnemyStunned>>,
+    config: Res<MovementConfig>,
+    texture_handles: Res<TextureHandles>,
+) {
+    let dt = physics_dt(&timesteps);
+    let player = q_player.get_single().ok();
+
+    for (entity, transform, mut shooter) in &mut q_shooters {
+        shooter.since_shot += dt;
+
+        let Some((player, player_transform)) = player else {
+            continue;
+        };
+        let (from, to) = (
+            transform.translation.truncate(),
+            player_transform.translation.truncate(),
+        );
+
+        if shooter.since_shot < config.enemy_shot_interval
+            || !in_sight(&rapier_context, entity, from, player, to, SHOOT_RANGE)
+        {
+            continue;
+        }
+        shooter.since_shot = 0.;
+
+        // from just outside the shooter, so it isn't in its own way
+        let dir = (to - from).normalize_or_zero();
+        spawn_projectile(
+            &mut commands,
+            &texture_handles,
+            from + dir * (PLAYER_RADIUS + PROJECTILE_RADIUS),
+            dir * config.enemy_shot_speed,
+        );
+    }
+}
+
+fn move_projectiles(
+    mut commands: Commands,
+    timesteps: Res<FixedTimesteps>,
+    rapier_context: Res<RapierContext>,
+    q_walls: Query<(), (With<Wall>, Without<OneWayPlatform>)>,
+    mut q_projectiles: Query<(Entity, &mut Transform, &Collider, &mut Projectile)>,
+) {
+    let dt = std::time::Duration::from_secs_f32(physics_dt(&timesteps));
+
+    for (entity, mut transform, collider, mut projectile) in &mut q_projectiles {
+        projectile.lifetime.tick(dt);
+
+        let hit_wall = rapier_context
+            .cast_shape(
+                transform.translation.truncate(),
+                0.,
+                projectile.velocity,
+                collider,
+                1.,
+                QueryFilter::default()
+                    .exclude_sensors()
+                    .predicate(&|e| q_walls.contains(e)),
+            )
+            .is_some();
+
+        if hit_wall || projectile.lifetime.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        transform.translation += projectile.velocity.extend(0.);
+    }
+}
+
+// projectiles are sensors so they don't push anyone around, which means the
+// controller never reports hitting them. They look for actors themselves
+#[allow(clippy::type_complexity)]
+fn projectile_hit_actor(
+    mut commands: Commands,
+    rapier_context: Res<RapierContext>,
+    q_projectiles: Query<(Entity, &Transform), (With<Projectile>, With<KillPlayerHitbox>)>,
+    q_actors: Query<(), (With<Actor>, Without<ActorDead>)>,
+) {
+    for (projectile, transform) in &q_projectiles {
+        for (a, b, intersecting) in rapier_context.intersections_with(projectile) {
+            let actor = if a == projectile { b } else { a };
+            if !q_actors.contains(actor) || !intersecting {
+                continue;
+            }
+
+            commands.entity(actor).insert(PlayerHurt {
+                from: transform.translation.truncate(),
+            });
+            commands.entity(projectile).despawn_recursive();
+            break;
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    prelude::*,
+    render::mesh::{PrimitiveTopology, VertexAttributeValues},
+    sprite::MaterialMesh2dBundle,
+};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    level::{BoxBottomRight, BoxTopLeft},
+    level_loader::{BoxData, SlopeData, SlopeShape},
+    InGameItem, TextureHandles,
+};
+
+// line segments making up the top of a hill
+const HILL_SEGMENTS: usize = 16;
+
+/// Ground with a sloped or rounded top. The collider is one of its parts, the
+/// entity itself only has the box it fills
+#[derive(Component)]
+pub struct Slope(pub SlopeShape);
+
+/// Collider and graphics of a slope, thrown away and rebuilt when it changes size
+#[derive(Component)]
+struct SlopePart;
+
+pub struct SlopesPlugin;
+
+impl Plugin for SlopesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(rebuild_slope_parts);
+    }
+}
+
+pub fn spawn_slope(
+    commands: &mut Commands,
+    slope: SlopeData,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) -> Entity {
+    let BoxData { tl, br } = slope.area;
+    let center = (tl + br) / 2.;
+
+    commands
+        .spawn(Slope(slope.shape))
+        .insert(InGameItem)
+        .insert(BoxTopLeft(tl))
+        .insert(BoxBottomRight(br))
+        .insert(TransformBundle::from_transform(
+            Transform::from_translation(center.extend(10.)),
+        ))
+        .insert(VisibilityBundle::default())
+        .with_children(|cb| {
+            spawn_slope_parts(cb, slope, texture_handles, meshes, materials);
+        })
+        .id()
+}
+
+/// Top of a `w` by `h` slope from left to right, centered on the origin
+fn slope_surface(shape: SlopeShape, w: f32, h: f32) -> Vec<Vec2> {
+    let (hx, hy) = (w / 2., h / 2.);
+
+    match shape {
+        SlopeShape::RampUp => vec![Vec2::new(-hx, -hy), Vec2::new(hx, hy)],
+        SlopeShape::RampDown => vec![Vec2::new(-hx, hy), Vec2::new(hx, -hy)],
+        // a cosine bump, so it meets the ground flat on both sides
+        SlopeShape::Hill => (0..=HILL_SEGMENTS)
+            .map(|i| {
+                let t = i as f32 / HILL_SEGMENTS as f32;
+                Vec2::new(-hx + w * t, -hy + h * (1. - (2. * PI * t).cos()) / 2.)
+            })
+            .collect(),
+    }
+}
+
+/// Triangles filling in under `surface` down to `bottom`, a strip of quads from
+/// each bit of the surface
+fn fill_triangles(surface: &[Vec2], bottom: f32) -> Vec<Vec2> {
+    let mut triangles = vec![];
+    for pair in surface.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        triangles.extend([
+            a,
+            Vec2::new(a.x, bottom),
+            b,
+            b,
+            Vec2::new(a.x, bottom),
+            Vec2::new(b.x, bottom),
+        ]);
+    }
+    triangles
+}
+
+/// Solid shape of a slope, centered on it. The slope's own collider is only its
+/// outline, which a point inside never touches
+pub fn slope_fill_collider(slope: SlopeData) -> Collider {
+    let BoxData { tl, br } = slope.area;
+    let w = (br.x - tl.x).abs();
+    let h = (br.y - tl.y).abs();
+
+    // ramps start or end on the bottom, leaving a triangle with no area that
+    // point queries get wrong
+    let vertices: Vec<Vec2> = fill_triangles(&slope_surface(slope.shape, w, h), -h / 2.)
+        .chunks(3)
+        .filter(|t| (t[1] - t[0]).perp_dot(t[2] - t[0]).abs() > f32::EPSILON)
+        .flatten()
+        .copied()
+        .collect();
+    let indices = (0..vertices.len() as u32 / 3)
+        .map(|i| [i * 3, i * 3 + 1, i * 3 + 2])
+        .collect();
+    Collider::trimesh(vertices, indices)
+}
+
+fn spawn_slope_parts(
+    cb: &mut ChildBuilder,
+    slope: SlopeData,
+    texture_handles: &Res<TextureHandles>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) {
+    let BoxData { tl, br } = slope.area;
+    let w = (br.x - tl.x).abs();
+    let h = (br.y - tl.y).abs();
+    let bottom = -h / 2.;
+
+    let surface = slope_surface(slope.shape, w, h);
+
+    // Collider
+    // the surface closed off along the bottom, skipping corners it already ends in
+    let mut outline = surface.clone();
+    let (first, last) = (surface[0], surface[surface.len() - 1]);
+    if last.y != bottom {
+        outline.push(Vec2::new(last.x, bottom));
+    }
+    if first.y != bottom {
+        outline.push(Vec2::new(first.x, bottom));
+    }
+    let n = outline.len() as u32;
+    let indices = (0..n).map(|i| [i, (i + 1) % n]).collect();
+
+    cb.spawn((Collider::polyline(outline, Some(indices)), SlopePart))
+        .insert(TransformBundle::default());
+
+    // Graphics
+    // FILL
+    let positions: Vec<[f32; 3]> = fill_triangles(&surface, bottom)
+        .into_iter()
+        .map(|p| [p.x, p.y, 0.])
+        .collect();
+
+    // same texture scale as box fills
+    let uvs: Vec<[f32; 2]> = positions.iter().map(|p| [p[0] / 5., -p[1] / 5.]).collect();
+    let normals = vec![[0., 0., 1.]; positions.len()];
+
+    let mut fill_mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    fill_mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    fill_mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    fill_mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+
+    cb.spawn(MaterialMesh2dBundle {
+        mesh: meshes.add(fill_mesh).into(),
+        material: materials.add(ColorMaterial {
+            texture: Some(texture_handles.chalk_box_fill.clone().unwrap()),
+            ..default()
+        }),
+        ..default()
+    })
+    .insert(SlopePart);
+
+    // TOP, drawn like the top of a box along every bit of the surface
+    for pair in surface.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let along = b - a;
+        let len = along.length();
+        let angle = along.y.atan2(along.x);
+        let up = Vec2::new(-angle.sin(), angle.cos());
+
+        let mut line_mesh = Mesh::from(shape::Quad::new(Vec2::new(len, 2.5)));
+        if let Some(VertexAttributeValues::Float32x2(uvs)) =
+            line_mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0)
+        {
+            for uv in uvs {
+                uv[0] *= 16. * len / 32.;
+                uv[1] *= 4.;
+            }
+        }
+
+        let position = (a + b) / 2. - up;
+        cb.spawn(MaterialMesh2dBundle {
+            mesh: meshes.add(line_mesh).into(),
+            transform: Transform::from_translation(position.extend(0.))
+                .with_rotation(Quat::from_rotation_z(angle)),
+            material: materials.add(ColorMaterial {
+                texture: Some(texture_handles.chalk_line_horizontal.clone().unwrap()),
+                ..default()
+            }),
+            ..default()
+        })
+        .insert(SlopePart);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn rebuild_slope_parts(
+    mut commands: Commands,
+    q_slopes: Query<
+        (
+            Entity,
+            &Slope,
+            &BoxTopLeft,
+            &BoxBottomRight,
+            ChangeTrackers<BoxTopLeft>,
+            Option<&Children>,
+        ),
+        Or<(Changed<BoxTopLeft>, Changed<BoxBottomRight>)>,
+    >,
+    q_parts: Query<Entity, With<SlopePart>>,
+    texture_handles: Res<TextureHandles>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, slope, tl, br, tl_tracker, children) in &q_slopes {
+        // spawn_slope already built these
+        if tl_tracker.is_added() {
+            continue;
+        }
+
+        for &child in children.into_iter().flatten() {
+            if q_parts.contains(child) {
+                commands.entity(child).despawn_recursive();
+            }
+        }
+
+        let slope = SlopeData {
+            area: BoxData { tl: tl.0, br: br.0 },
+            shape: slope.0,
+        };
+        commands.entity(entity).with_children(|cb| {
+            spawn_slope_parts(cb, slope, &texture_handles, &mut meshes, &mut materials);
+        });
+    }
+}
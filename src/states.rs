@@ -4,6 +4,7 @@ use iyes_loopless::prelude::AppLooplessStateExt;
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum GameState {
     MainMenu,
+    LevelSelect,
     InGame,
     LevelEditor,
     Dead,
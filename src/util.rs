@@ -7,3 +7,8 @@ pub fn despawn_with<T: Component>(mut commands: Commands, q: Query<Entity, With<
         commands.entity(e).despawn_recursive();
     }
 }
+
+/// Remove a resource, if it's there
+pub fn remove_resource<T: Resource>(mut commands: Commands) {
+    commands.remove_resource::<T>();
+}